@@ -11,7 +11,9 @@ pub enum AnalysisError {
     NotFound(String),
     IoError(String),
     ParseError(String),
-    RegError(String)
+    RegError(String),
+    /// 请求参数不合法，属于调用方的错误
+    InvalidParam(String)
 }
 
 
@@ -41,6 +43,37 @@ pub enum FrameError {
     Unknown(String),
 }
 
+/// 文件内容解析错误，line为出错的行号（从1开始），None表示无法定位到具体行
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    pub line: Option<usize>,
+    pub reason: String,
+}
+
+/// 导入时被跳过的文件及原因，随解析任务的结果一起返回
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseWarning {
+    pub file: String,
+    pub line: Option<usize>,
+    pub reason: String,
+}
+
+impl LineError {
+    pub fn new(line: Option<usize>, reason: impl Into<String>) -> Self {
+        LineError { line, reason: reason.into() }
+    }
+}
+
+impl ParseWarning {
+    pub fn new(file: &str, line: Option<usize>, reason: impl Into<String>) -> Self {
+        ParseWarning { file: file.to_string(), line, reason: reason.into() }
+    }
+
+    pub fn from_line(file: &str, error: LineError) -> Self {
+        ParseWarning { file: file.to_string(), line: error.line, reason: error.reason }
+    }
+}
+
 impl AnalysisError {
     fn error_response(&self) -> String {
         match self {
@@ -68,6 +101,10 @@ impl AnalysisError {
                 println!("Regex error:{:?}", msg);
                 msg.into()
             }
+            AnalysisError::InvalidParam(msg) => {
+                println!("Invalid param:{:?}", msg);
+                msg.into()
+            }
         }
     }
 }
@@ -76,6 +113,7 @@ impl error::ResponseError for AnalysisError {
     fn status_code(&self) -> StatusCode {
         match self {
             AnalysisError::NotFound(_msg) => StatusCode::NOT_FOUND,
+            AnalysisError::InvalidParam(_msg) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR
         }
     }
@@ -89,7 +127,15 @@ impl error::ResponseError for AnalysisError {
 
 impl fmt::Display for AnalysisError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self)
+        match self {
+            AnalysisError::DBError(msg) => write!(f, "Database error: {}", msg),
+            AnalysisError::ActixError(msg) => write!(f, "Server error: {}", msg),
+            AnalysisError::NotFound(msg) => write!(f, "Not found: {}", msg),
+            AnalysisError::IoError(msg) => write!(f, "Io error: {}", msg),
+            AnalysisError::ParseError(msg) => write!(f, "Parse error: {}", msg),
+            AnalysisError::RegError(msg) => write!(f, "Regex error: {}", msg),
+            AnalysisError::InvalidParam(msg) => write!(f, "Invalid param: {}", msg),
+        }
     }
 }
 
@@ -1,60 +1,401 @@
+use std::collections::HashMap;
 use std::fs::File;
+use std::sync::Mutex;
 use std::{fs, io};
-use std::io::{copy, Error, Read};
-use std::path::Path;
+use std::io::{copy, BufRead, BufReader, Cursor, Error, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
 
-use crate::model::file_info::FileInfo;
+use flate2::read::MultiGzDecoder;
+use zip::ZipArchive;
 
+use crate::error::ParseWarning;
+use crate::model::{file_info::FileInfo, file_rule::{FileClassifier, SNIFF_BYTES}, line_index::LineIndex};
 
-/// 从文件夹或者压缩包中提取信息
-pub fn unzip_and_extract_file(source: &Path, work_space: &str) -> io::Result<Vec<FileInfo>> {
-    let target: &Path = source.parent().expect("获取压缩包的上级路径错误");
-    let zip_file = fs::File::open(&source)?;
-    let mut zip = zip::ZipArchive::new(zip_file)?;
+/// 支持解压的压缩包类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    /// gzip压缩，解压后可能是tar包，也可能是单个文件
+    Gzip,
+}
 
-    if !target.exists() {
-        fs::create_dir_all(target)?;
+impl ArchiveKind {
+    /// 根据文件头判断压缩包类型，不是支持的压缩包时返回None
+    pub fn detect(path: &Path) -> io::Result<Option<ArchiveKind>> {
+        let mut head = Vec::with_capacity(512);
+        File::open(path)?.take(512).read_to_end(&mut head)?;
+        Ok(Self::from_head(&head))
     }
-    let mut file_mapping:Vec<FileInfo> = vec![];
 
+    fn from_head(head: &[u8]) -> Option<ArchiveKind> {
+        match head {
+            [0x50, 0x4B, 0x03, 0x04, ..] => Some(ArchiveKind::Zip),
+            [0x1F, 0x8B, ..] => Some(ArchiveKind::Gzip),
+            _ if is_tar(head) => Some(ArchiveKind::Tar),
+            _ => None,
+        }
+    }
+}
+
+/// 嵌套压缩包的最大解压层数，超过后嵌套的压缩包作为普通文件保留，防止压缩包自包含时无限递归
+pub const MAX_NESTED_DEPTH: usize = 3;
+
+/// 解压过程中剩余可写入的字节数，`max`为一个压缩包最多解压出的字节数
+struct ExtractLimit {
+    max: u64,
+    remaining: u64,
+}
+
+impl ExtractLimit {
+    fn new(max: u64) -> Self {
+        ExtractLimit { max, remaining: max }
+    }
+
+    /// 复制数据并扣减剩余字节数，超过上限时返回`FileTooLarge`错误
+    fn copy(&mut self, reader: &mut impl Read, writer: &mut impl Write) -> io::Result<u64> {
+        let copied = copy(&mut reader.take(self.remaining.saturating_add(1)), writer)?;
+        if copied > self.remaining {
+            return Err(Error::new(
+                io::ErrorKind::FileTooLarge,
+                format!("解压后的内容超过{}字节上限", self.max),
+            ));
+        }
+        self.remaining -= copied;
+        Ok(copied)
+    }
+}
+
+/// tar包在第257个字节处有"ustar"标识
+fn is_tar(head: &[u8]) -> bool {
+    head.get(257..262) == Some(b"ustar".as_slice())
+}
+
+/// 从压缩包中提取信息，支持zip、tar、tar.gz、tgz和单个文件的gz，压缩包中嵌套的压缩包会继续解压，
+/// 最多解压`MAX_NESTED_DEPTH`层，解压出的内容不超过`max_bytes`字节。
+/// zip中的普通文件不解压，解析时通过`with_reader`直接从压缩包中读取
+/// # Arguments
+/// * `source` - 压缩包路径
+/// * `target` - 解压目录，压缩包中的文件只会写入该目录
+/// * `work_space` - 工作空间ID
+/// * `classifier` - 文件分类规则
+/// * `max_bytes` - 最多解压出的字节数，防止压缩炸弹写满磁盘
+/// # Returns
+/// * `io::Result<(Vec<FileInfo>, Vec<ParseWarning>)>` - 解压出的文件，以及超过嵌套层数或解压失败而被跳过的嵌套压缩包；
+///   压缩包中包含`..`或绝对路径的条目，或解压内容超过上限时返回错误
+pub fn unzip_and_extract_file(
+    source: &Path,
+    target: &Path,
+    work_space: &str,
+    classifier: &FileClassifier,
+    max_bytes: u64,
+) -> io::Result<(Vec<FileInfo>, Vec<ParseWarning>)> {
+    let kind = ArchiveKind::detect(source)?
+        .ok_or_else(|| Error::new(io::ErrorKind::InvalidData, "不支持的压缩包类型"))?;
+    let mut limit = ExtractLimit::new(max_bytes);
+    let mut warnings = vec![];
+    if kind == ArchiveKind::Zip {
+        let files = scan_zip(source, target, work_space, classifier, &mut limit, &mut warnings)?;
+        return Ok((files, warnings));
+    }
+    let files = extract_archive(source, kind, target, target, 0, &mut limit, &mut warnings)?;
+    let files = files
+        .iter()
+        .map(|path| FileInfo::new(path, work_space, classifier))
+        .collect();
+    Ok((files, warnings))
+}
+
+/// 解压到target目录并返回解压出的文件，单个文件的gz解压到file_dir目录。
+/// 嵌套的压缩包解压到去掉扩展名的同名目录中，并以其中的文件代替压缩包本身，`depth`为当前压缩包的嵌套层数
+fn extract_archive(
+    source: &Path,
+    kind: ArchiveKind,
+    target: &Path,
+    file_dir: &Path,
+    depth: usize,
+    limit: &mut ExtractLimit,
+    warnings: &mut Vec<ParseWarning>,
+) -> io::Result<Vec<PathBuf>> {
+    let extracted = match kind {
+        ArchiveKind::Zip => extract_zip(source, target, limit)?,
+        ArchiveKind::Tar => extract_tar(File::open(source)?, target, limit)?,
+        ArchiveKind::Gzip => extract_gzip(source, target, file_dir, limit)?,
+    };
+    let mut files = vec![];
+    for path in extracted {
+        match ArchiveKind::detect(&path) {
+            Ok(Some(nested)) if depth < MAX_NESTED_DEPTH => {
+                let file_dir = path.parent().unwrap_or(target);
+                extract_nested(&path, nested, file_dir, depth + 1, limit, warnings, |mut children| files.append(&mut children))?;
+            }
+            Ok(Some(_)) => {
+                warnings.push(ParseWarning::new(
+                    &path.to_string_lossy(),
+                    None,
+                    format!("嵌套压缩包超过{}层，未解压", MAX_NESTED_DEPTH),
+                ));
+                files.push(path);
+            }
+            _ => files.push(path),
+        }
+    }
+    Ok(files)
+}
+
+/// 解压嵌套的压缩包，失败时只跳过该压缩包并记录警告，超过解压上限时终止整个解压过程
+fn extract_nested(
+    path: &Path,
+    kind: ArchiveKind,
+    file_dir: &Path,
+    depth: usize,
+    limit: &mut ExtractLimit,
+    warnings: &mut Vec<ParseWarning>,
+    on_extracted: impl FnOnce(Vec<PathBuf>),
+) -> io::Result<()> {
+    match extract_archive(path, kind, &nested_dir(path), file_dir, depth, limit, warnings) {
+        Ok(children) => on_extracted(children),
+        Err(e) if e.kind() == io::ErrorKind::FileTooLarge => return Err(e),
+        Err(e) => warnings.push(ParseWarning::new(&path.to_string_lossy(), None, format!("解压嵌套压缩包失败:{}", e))),
+    }
+    Ok(())
+}
+
+/// 遍历zip中的条目，只读取开头用于判断文件类型，嵌套的压缩包无法在压缩包内随机读取，写入target目录后再解压
+fn scan_zip(
+    source: &Path,
+    target: &Path,
+    work_space: &str,
+    classifier: &FileClassifier,
+    limit: &mut ExtractLimit,
+    warnings: &mut Vec<ParseWarning>,
+) -> io::Result<Vec<FileInfo>> {
+    let mut zip = ZipArchive::new(File::open(source)?)?;
+    let mut files = vec![];
     for i in 0..zip.len() {
-        match zip.by_index(i) {
-            Ok(mut file) => {
-                let file_name = file.name();
-                if file.is_dir() {
-                    let target_dir = target.join(file_name.replace("\\", ""));
-                    if let Err(e) = fs::create_dir_all(&target_dir) {
-                        eprintln!("Failed to create directory {:?}: {}", target_dir, e);
-                    }
-                } else {
-                    let file_path = target.join(file_name);
-                    let exist;
-                    match if file_path.exists() {
-                        exist = true;
-                        fs::File::open(&file_path)
-                    } else {
-                        exist = false;
-                        fs::File::create(&file_path)
-                    } {
-                        Ok(mut target_file) => {
-                            if !exist {
-                                if let Err(e) = copy(&mut file, &mut target_file) {
-                                    eprintln!("Failed to copy file to {:?}: {}", file_path, e);
-                                }    
-                            }
-                            file_mapping.push(FileInfo::new(&file_path, work_space));
-                        }
-                        Err(e) => eprintln!("Failed to open or create file {:?}: {}", file_path, e),
-                    }
-                }
+        let mut entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        // Windows下创建的压缩包使用反斜杠作为分隔符
+        let relative = safe_entry_path(Path::new(&name.replace('\\', "/")))?;
+        let mut head = Vec::with_capacity(SNIFF_BYTES);
+        (&mut entry).take(SNIFF_BYTES as u64).read_to_end(&mut head)?;
+        let Some(kind) = ArchiveKind::from_head(&head) else {
+            files.push(FileInfo::in_archive(source, &name, work_space, classifier.classify_head(&relative, &head)));
+            continue;
+        };
+        let path = target.join(&relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&path)?;
+        limit.copy(&mut Cursor::new(head).chain(&mut entry), &mut file)?;
+        let file_dir = path.parent().unwrap_or(target);
+        extract_nested(&path, kind, file_dir, 1, limit, warnings, |children| {
+            files.extend(children.iter().map(|child| FileInfo::new(child, work_space, classifier)))
+        })?;
+    }
+    Ok(files)
+}
+
+/// 打开待解析文件的读取器，缓存已经打开的zip压缩包，避免读取每个条目时都重新解析压缩包的目录
+/// 并行读取时每个线程从缓存中取走一个压缩包，读完后放回，同一个压缩包最多同时打开线程数个
+#[derive(Default)]
+pub struct ArchiveReaders {
+    opened: Mutex<HashMap<String, Vec<ZipArchive<File>>>>,
+}
+
+impl ArchiveReaders {
+    /// 打开待解析的文件并定位到`offset`字节处，`archive`不为空时`path`为zip中的条目名，直接从压缩包中解压读取
+    pub fn with_reader_at<T>(
+        &self,
+        path: &str,
+        archive: Option<&str>,
+        offset: u64,
+        read: impl FnOnce(&mut dyn BufRead) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let Some(archive) = archive else {
+            let mut file = File::open(path)?;
+            file.seek(SeekFrom::Start(offset))?;
+            return read(&mut BufReader::new(file));
+        };
+        let cached = self.opened.lock().unwrap().get_mut(archive).and_then(Vec::pop);
+        let mut zip = match cached {
+            Some(zip) => zip,
+            None => ZipArchive::new(File::open(archive)?)?,
+        };
+        let result = Self::read_entry(&mut zip, path, offset, read);
+        self.opened.lock().unwrap().entry(archive.to_string()).or_default().push(zip);
+        result
+    }
+
+    /// 从头读取待解析的文件，见`with_reader_at`
+    pub fn with_reader<T>(&self, path: &str, archive: Option<&str>, read: impl FnOnce(&mut dyn BufRead) -> io::Result<T>) -> io::Result<T> {
+        self.with_reader_at(path, archive, 0, read)
+    }
+
+    fn read_entry<T>(
+        zip: &mut ZipArchive<File>,
+        path: &str,
+        offset: u64,
+        read: impl FnOnce(&mut dyn BufRead) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let mut reader = BufReader::new(zip.by_name(path)?);
+        // 压缩后的数据无法直接定位，解压并跳过偏移之前的内容
+        copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+        read(&mut reader)
+    }
+}
+
+/// 打开待解析的文件并定位到`offset`字节处，只读取一次时使用，多次读取同一压缩包时使用`ArchiveReaders`
+pub fn with_reader_at<T>(
+    path: &str,
+    archive: Option<&str>,
+    offset: u64,
+    read: impl FnOnce(&mut dyn BufRead) -> io::Result<T>,
+) -> io::Result<T> {
+    ArchiveReaders::default().with_reader_at(path, archive, offset, read)
+}
+
+/// 从头读取待解析的文件，见`with_reader_at`
+pub fn with_reader<T>(path: &str, archive: Option<&str>, read: impl FnOnce(&mut dyn BufRead) -> io::Result<T>) -> io::Result<T> {
+    with_reader_at(path, archive, 0, read)
+}
+
+/// 读取文件中第`start_line`到`end_line`行（从1开始，包含两端），有行索引时从最近的索引点开始读取
+pub fn read_line_range(
+    path: &str,
+    archive: Option<&str>,
+    index: Option<&LineIndex>,
+    start_line: usize,
+    end_line: usize,
+) -> io::Result<Vec<String>> {
+    let (mut line_number, offset) = index.map(|index| index.seek_point(start_line)).unwrap_or((1, 0));
+    with_reader_at(path, archive, offset, |reader| {
+        let mut lines = vec![];
+        let mut buffer = Vec::new();
+        while line_number <= end_line {
+            buffer.clear();
+            if reader.read_until(b'\n', &mut buffer)? == 0 {
+                break;
+            }
+            if line_number >= start_line {
+                lines.push(String::from_utf8_lossy(trim_line_end(&buffer)).into_owned());
+            }
+            line_number += 1;
+        }
+        Ok(lines)
+    })
+}
+
+/// 去掉行尾的换行符
+pub fn trim_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// 校验压缩包中的条目路径，只允许普通的相对路径，防止写到解压目录之外
+fn safe_entry_path(name: &Path) -> io::Result<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("压缩包中的路径不安全:{}", name.display()),
+                ))
             }
-            Err(e) => eprintln!("Failed to process file at index {}: {:?}", i, e)
         }
     }
-    Ok(file_mapping)
+    if path.as_os_str().is_empty() {
+        return Err(Error::new(io::ErrorKind::InvalidData, "压缩包中的路径为空"));
+    }
+    Ok(path)
+}
+
+fn extract_zip(source: &Path, target: &Path, limit: &mut ExtractLimit) -> io::Result<Vec<PathBuf>> {
+    let zip_file = fs::File::open(source)?;
+    let mut zip = ZipArchive::new(zip_file)?;
+    fs::create_dir_all(target)?;
+    let mut files: Vec<PathBuf> = vec![];
+
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        // Windows下创建的压缩包使用反斜杠作为分隔符
+        let path = target.join(safe_entry_path(Path::new(&file.name().replace('\\', "/")))?);
+        if file.is_dir() {
+            fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        limit.copy(&mut file, &mut File::create(&path)?)?;
+        files.push(path);
+    }
+    Ok(files)
 }
 
-pub fn extract_file(target: &Path, work_space: &str)  -> io::Result<Vec<FileInfo>> {
+fn extract_tar<R: Read>(reader: R, target: &Path, limit: &mut ExtractLimit) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(target)?;
+    let mut archive = tar::Archive::new(reader);
+    let mut files = vec![];
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = target.join(safe_entry_path(&entry.path()?)?);
+        if !entry.header().entry_type().is_file() {
+            // 目录在解压文件时按需创建，链接等其他类型的条目直接跳过
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        limit.copy(&mut entry, &mut File::create(&path)?)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// gzip解压后是tar包时解压到target目录，否则解压为file_dir目录中去掉.gz的同名文件
+fn extract_gzip(source: &Path, target: &Path, file_dir: &Path, limit: &mut ExtractLimit) -> io::Result<Vec<PathBuf>> {
+    let mut decoder = MultiGzDecoder::new(File::open(source)?);
+    let mut head = Vec::with_capacity(512);
+    (&mut decoder).take(512).read_to_end(&mut head)?;
+    let is_tar = is_tar(&head);
+    let mut reader = Cursor::new(head).chain(decoder);
+    if is_tar {
+        return extract_tar(reader, target, limit);
+    }
+    let name = source.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let output = match name.strip_suffix(".gz").filter(|stem| !stem.is_empty()) {
+        Some(stem) => file_dir.join(stem),
+        None => file_dir.join(format!("{}.out", name)),
+    };
+    fs::create_dir_all(file_dir)?;
+    limit.copy(&mut reader, &mut File::create(&output)?)?;
+    Ok(vec![output])
+}
+
+/// 嵌套压缩包的解压目录，如a.tar.gz解压到a目录
+fn nested_dir(path: &Path) -> PathBuf {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let lower = name.to_ascii_lowercase();
+    let stem = [".tar.gz", ".tgz", ".tar", ".zip", ".gz"]
+        .iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map(|ext| &name[..name.len() - ext.len()])
+        .filter(|stem| !stem.is_empty());
+    match stem {
+        Some(stem) => path.with_file_name(stem),
+        None => path.with_file_name(format!("{}_extracted", name)),
+    }
+}
+
+pub fn extract_file(target: &Path, work_space: &str, classifier: &FileClassifier) -> io::Result<Vec<FileInfo>> {
     let mut file_mapping:Vec<FileInfo> = vec![];
     if target.is_dir() {
         for entry in fs::read_dir(target)?{
@@ -63,20 +404,20 @@ pub fn extract_file(target: &Path, work_space: &str)  -> io::Result<Vec<FileInfo
 
             if path.is_dir() {
                 // 如果是文件夹，递归处理
-                let mut children = extract_file(&path, work_space)?;
+                let mut children = extract_file(&path, work_space, classifier)?;
                 if children.len() > 0 {
                     file_mapping.append(&mut children);
                 }
             } else if path.is_file() {
                 // 如果是文件，读取内容
-                file_mapping.push(FileInfo::new(&entry.path(), work_space))
+                file_mapping.push(FileInfo::new(&entry.path(), work_space, classifier))
             }
         }
     }
     Ok(file_mapping)
 }
 
-    
+
 /**
  * 获取选中的路径类型，是文件夹还是压缩包
  */
@@ -85,43 +426,196 @@ pub fn get_file_type(path: &str) -> Result<u8, Error> {
   if meta_data.is_dir() {
       return Ok(1);
   }
-
-  let mut file = File::open(path).unwrap();
-  let mut buffer = [0; 5];
-  let bytes_read = file.read(&mut buffer)?;
-  if bytes_read < buffer.len() {
+  if meta_data.len() == 0 {
       return Err(Error::new(io::ErrorKind::InvalidData, "文件长度为0"));
   }
-  if !check_file_type(&buffer) {
-      return Err(Error::new(io::ErrorKind::InvalidData, "非法的文件类型"));
+  match ArchiveKind::detect(Path::new(path))? {
+      Some(_) => Ok(0),
+      None => Err(Error::new(io::ErrorKind::InvalidData, "非法的文件类型")),
   }
-  Ok(0)
 }
-/**
- * 检查压缩包类型
- */
-pub fn check_file_type(buffer: &[u8; 5]) -> bool {
-  match buffer {
-      [0x50, 0x4B, 0x03, 0x04, ..] => {
-          println!("This is a ZIP file");
-          true
-      }
-      [0x1F, 0x8B, ..] => {
-          println!("This is a GZIP file");
-          true
-      }
-      [0x42, 0x5A, 0x68, ..] => {
-          println!("This is a BZIP2 file");
-          true
-      }
-      [0x52, 0x61, 0x72, 0x21, ..] => {
-          println!("This is a RAR file");
-          true
-      }
-      [0x75, 0x73, 0x74, 0x61, 0x72, ..] => {
-          println!("This is a TAR file");
-          true
-      }
-      _ => false,
-  }
-}
\ No newline at end of file
+
+#[cfg(test)]
+pub mod tests {
+    use std::{env, io::Write};
+
+    use flate2::{write::GzEncoder, Compression};
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::model::file_info::FileType;
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn gz(content: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    pub fn test_extract_nested_archives() {
+        let dir = env::temp_dir().join(format!("file_utils_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let bundle = tar_gz(&[
+            ("./20240809_170000/cpu_top.log", b"top - 17:00:00 up 1 day"),
+            ("./20240809_170000/threaddump_20240809_170000.log", b"Full thread dump"),
+        ]);
+        let source = dir.join("capture.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&source).unwrap());
+        zip.start_file("bundle.tar.gz", SimpleFileOptions::default()).unwrap();
+        zip.write_all(&bundle).unwrap();
+        zip.start_file("gc.log.gz", SimpleFileOptions::default()).unwrap();
+        zip.write_all(&gz(b" S0C    S1C")).unwrap();
+        zip.start_file("20240809_170236/threaddump_20240809_170236.log", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"Full thread dump").unwrap();
+        zip.finish().unwrap();
+
+        assert_eq!(get_file_type(source.to_str().unwrap()).unwrap(), 0);
+        let target = dir.join("ws");
+        let (files, warnings) = unzip_and_extract_file(&source, &target, "ws", &FileClassifier::default(), u64::MAX).unwrap();
+        assert!(warnings.is_empty());
+        let mut found: Vec<(String, FileType)> = files
+            .iter()
+            .filter(|f| f.archive.is_none())
+            .map(|f| (f.path.strip_prefix(target.to_str().unwrap()).unwrap().to_string(), f.file_type.clone()))
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(found, vec![
+            ("/bundle/20240809_170000/cpu_top.log".to_string(), FileType::CpuTop),
+            ("/bundle/20240809_170000/threaddump_20240809_170000.log".to_string(), FileType::StackTrace),
+            ("/gc.log".to_string(), FileType::Gc),
+        ]);
+        // zip中的普通文件不解压，直接从压缩包中读取
+        let entry = files.iter().find(|f| f.archive.is_some()).unwrap();
+        assert_eq!(entry.path, "20240809_170236/threaddump_20240809_170236.log");
+        assert_eq!(entry.file_type, FileType::StackTrace);
+        assert!(entry.time.is_some());
+        assert!(!target.join(&entry.path).exists());
+        let content = with_reader(&entry.path, entry.archive.as_deref(), |reader| {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            Ok(content)
+        }).unwrap();
+        assert_eq!(content, "Full thread dump");
+        assert!(files.iter().all(|f| f.file_type != FileType::StackTrace || f.time.is_some()));
+
+        let tgz = dir.join("capture.tgz");
+        fs::write(&tgz, tar_gz(&[("gcutil.log", b"  S0     S1")])).unwrap();
+        let (files, _) = unzip_and_extract_file(&tgz, &dir.join("tgz"), "ws", &FileClassifier::default(), u64::MAX).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].file_type, FileType::GcUtil);
+        assert!(files[0].path.starts_with(dir.join("tgz").to_str().unwrap()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_read_line_range() {
+        let dir = env::temp_dir().join(format!("file_utils_lines_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let content: String = (1..=10).map(|i| format!("line {}\r\n", i)).collect();
+        let mut index = LineIndex::new(3);
+        let mut offset = 0;
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            index.record(i + 1, offset);
+            offset += line.len() as u64;
+        }
+        let path = dir.join("dump.log");
+        fs::write(&path, &content).unwrap();
+        let source = dir.join("dump.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&source).unwrap());
+        zip.start_file("dump.log", SimpleFileOptions::default()).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let expected = vec!["line 5", "line 6", "line 7"];
+        let path = path.to_str().unwrap();
+        assert_eq!(read_line_range(path, None, None, 5, 7).unwrap(), expected);
+        assert_eq!(read_line_range(path, None, Some(&index), 5, 7).unwrap(), expected);
+        let archive = source.to_str();
+        assert_eq!(read_line_range("dump.log", archive, Some(&index), 5, 7).unwrap(), expected);
+        assert_eq!(read_line_range("dump.log", archive, Some(&index), 9, 20).unwrap(), vec!["line 9", "line 10"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_archive_readers() {
+        let dir = env::temp_dir().join(format!("file_utils_readers_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("dump.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&source).unwrap());
+        zip.start_file("a.log", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"line a").unwrap();
+        zip.start_file("b.log", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"line b").unwrap();
+        zip.finish().unwrap();
+
+        let readers = ArchiveReaders::default();
+        let archive = source.to_str();
+        let read = |name: &str| readers.with_reader(name, archive, |reader| reader.lines().collect::<io::Result<Vec<String>>>());
+        assert_eq!(read("a.log").unwrap(), vec!["line a"]);
+        assert_eq!(read("b.log").unwrap(), vec!["line b"]);
+        assert!(read("c.log").is_err());
+        // 顺序读取时只打开一次压缩包
+        assert_eq!(readers.opened.lock().unwrap()[archive.unwrap()].len(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_reject_unsafe_entry() {
+        let dir = env::temp_dir().join(format!("file_utils_unsafe_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("evil.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&source).unwrap());
+        zip.start_file("../escaped.log", SimpleFileOptions::default()).unwrap();
+        zip.write_all(b"overwritten").unwrap();
+        zip.finish().unwrap();
+
+        let target = dir.join("ws");
+        assert!(unzip_and_extract_file(&source, &target, "ws", &FileClassifier::default(), u64::MAX).is_err());
+        assert!(!dir.join("escaped.log").exists());
+
+        assert!(safe_entry_path(Path::new("/etc/passwd")).is_err());
+        assert!(safe_entry_path(Path::new("a/../../b")).is_err());
+        assert_eq!(safe_entry_path(Path::new("./a/b.log")).unwrap(), PathBuf::from("a/b.log"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    pub fn test_extract_limits() {
+        let dir = env::temp_dir().join(format!("file_utils_limit_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut content = b"Full thread dump".to_vec();
+        for _ in 0..MAX_NESTED_DEPTH + 2 {
+            content = gz(&content);
+        }
+        let source = dir.join("deep.log.gz");
+        fs::write(&source, content).unwrap();
+        let (files, warnings) = unzip_and_extract_file(&source, &dir.join("ws"), "ws", &FileClassifier::default(), u64::MAX).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(ArchiveKind::detect(Path::new(&files[0].path)).unwrap(), Some(ArchiveKind::Gzip));
+        // 超过嵌套层数的压缩包作为警告返回
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].file, files[0].path);
+
+        // 解压内容超过上限时终止解压
+        assert_eq!(
+            unzip_and_extract_file(&source, &dir.join("small"), "ws", &FileClassifier::default(), 4).unwrap_err().kind(),
+            io::ErrorKind::FileTooLarge
+        );
+        let mut limit = ExtractLimit::new(5);
+        assert_eq!(limit.copy(&mut b"12345".as_slice(), &mut io::sink()).unwrap(), 5);
+        let err = limit.copy(&mut b"6".as_slice(), &mut io::sink()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,4 +3,3 @@ pub mod error;
 pub mod string_utils;
 pub mod file_utils;
 pub mod model;
-pub mod config;
\ No newline at end of file
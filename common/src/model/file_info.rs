@@ -1,10 +1,10 @@
 use actix_web::web::{self};
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{fs, path::{Path, PathBuf}};
 
-use crate::{config::EnvVars, error::ThreadError, string_utils::rand_id, time_utils::parse_thread_time};
+use crate::{error::ThreadError, model::file_rule::FileClassifier, string_utils::rand_id, time_utils::parse_thread_time};
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -14,6 +14,14 @@ pub struct FileInfo {
     pub path: String,
     pub file_type: FileType,
     pub time: Option<NaiveDateTime>,
+    /// 文件所在的压缩包，不为空时path为压缩包中的条目名，解析时直接从压缩包中读取
+    #[serde(default)]
+    pub archive: Option<String>,
+    /// 文件大小和修改时间（毫秒时间戳），重新扫描时用于判断文件是否变化，压缩包中的文件为None
+    #[serde(default)]
+    pub size: Option<i64>,
+    #[serde(default)]
+    pub modified: Option<i64>,
 }
 
 lazy_static::lazy_static! {
@@ -27,15 +35,39 @@ impl From<web::Json<FileInfo>> for FileInfo {
 }
 
 impl FileInfo {
-    pub fn new(path: &PathBuf, work_space: &str) -> Self {
+    pub fn new(path: &PathBuf, work_space: &str, classifier: &FileClassifier) -> Self {
+        let mut info = Self::build(path, work_space, classifier.classify(path), None);
+        if let Ok(metadata) = fs::metadata(path) {
+            info.size = Some(metadata.len() as i64);
+            info.modified = metadata
+                .modified()
+                .ok()
+                .map(|modified| DateTime::<Utc>::from(modified).timestamp_millis());
+        }
+        info
+    }
+
+    /// 压缩包中的文件，`entry`为条目在压缩包中的名称
+    pub fn in_archive(archive: &Path, entry: &str, work_space: &str, file_type: FileType) -> Self {
+        let archive = archive.to_str().expect("Invalid Path").to_string();
+        Self::build(Path::new(entry), work_space, file_type, Some(archive))
+    }
+
+    fn build(path: &Path, work_space: &str, file_type: FileType, archive: Option<String>) -> Self {
         let file_name = path
             .file_name()
             .and_then(|os_str| os_str.to_str())
             .expect("Invalid Path");
-        let env_vars = EnvVars::load();
-        let file_type = FileType::new(file_name, &env_vars);
-        let time = if file_name.contains(&env_vars.thread_dump) {
+        let time = if file_type == FileType::StackTrace {
             Self::extract_time_info(file_name)
+        } else if matches!(file_type, FileType::CpuThread | FileType::CpuTop | FileType::GcUtil) {
+            // top和jstat的输出文件名中通常没有时间，采集时间取自所在目录名
+            Self::extract_time_info(file_name).or_else(|| {
+                path.parent()
+                    .and_then(|parent| parent.file_name())
+                    .and_then(|name| name.to_str())
+                    .and_then(Self::extract_time_info)
+            })
         } else {
             None
         };
@@ -45,6 +77,9 @@ impl FileInfo {
             path: path.to_str().expect("Invalid Path").to_string(),
             file_type,
             time,
+            archive,
+            size: None,
+            modified: None,
         }
     }
 
@@ -70,19 +105,6 @@ pub enum FileType {
     None,
 }
 
-impl FileType {
-    pub fn new(file_name: &str, env: &EnvVars) -> Self {
-        match file_name {
-            name if name.contains(&env.cpu_file) => FileType::CpuThread,
-            name if name.contains(&env.cpu_top) => FileType::CpuTop,
-            name if name.contains(&env.thread_dump) => FileType::StackTrace,
-            name if name.contains(&env.gc_util) => FileType::GcUtil,
-            name if name.contains(&env.gc) => FileType::Gc,
-            _ => FileType::None,
-        }
-    }
-}
-
 impl From<web::Json<FileType>> for FileType {
     fn from(file_type: web::Json<FileType>) -> Self {
         file_type.into_inner()
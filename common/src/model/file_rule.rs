@@ -0,0 +1,201 @@
+use std::{fs::File, io::Read, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::AnalysisError, model::file_info::FileType};
+
+/// 内容嗅探时读取文件开头的字节数
+pub const SNIFF_BYTES: usize = 4096;
+
+/// 文件分类规则，glob、regex匹配文件名，content匹配文件开头的内容，
+/// 同一条规则中配置的条件需同时满足
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FileRule {
+    pub glob: Option<String>,
+    pub regex: Option<String>,
+    pub content: Option<String>,
+    pub file_type: FileType,
+}
+
+impl FileRule {
+    fn name(glob: &str, file_type: FileType) -> Self {
+        FileRule { glob: Some(glob.to_string()), regex: None, content: None, file_type }
+    }
+
+    fn content(content: &str, file_type: FileType) -> Self {
+        FileRule { glob: None, regex: None, content: Some(content.to_string()), file_type }
+    }
+
+    /// 未配置分类规则时使用的默认规则，与config/default.toml保持一致
+    pub fn defaults() -> Vec<FileRule> {
+        vec![
+            FileRule::name("*cpu_thread*", FileType::CpuThread),
+            FileRule::name("*cpu_top*", FileType::CpuTop),
+            FileRule::name("*threaddump*", FileType::StackTrace),
+            FileRule::name("*gcutil*", FileType::GcUtil),
+            FileRule::name("*gc*", FileType::Gc),
+            FileRule::content("Full thread dump", FileType::StackTrace),
+            FileRule::content("S0C", FileType::Gc),
+        ]
+    }
+}
+
+struct CompiledRule {
+    name: Vec<Regex>,
+    content: Option<String>,
+    file_type: FileType,
+}
+
+/// 按顺序匹配分类规则，第一条命中的规则决定文件类型
+pub struct FileClassifier {
+    rules: Vec<CompiledRule>,
+}
+
+impl FileClassifier {
+    /// 编译分类规则
+    /// # Arguments
+    /// * `rules` - 按优先级排列的分类规则
+    /// # Returns
+    /// * `Result<FileClassifier, AnalysisError>` - 规则中的glob或正则表达式非法，或者规则没有任何条件时返回错误
+    pub fn new(rules: &[FileRule]) -> Result<Self, AnalysisError> {
+        let mut compiled = Vec::with_capacity(rules.len());
+        for (idx, rule) in rules.iter().enumerate() {
+            let mut name = vec![];
+            if let Some(glob) = &rule.glob {
+                name.push(Self::compile(&glob_to_regex(glob), idx)?);
+            }
+            if let Some(regex) = &rule.regex {
+                name.push(Self::compile(regex, idx)?);
+            }
+            if name.is_empty() && rule.content.is_none() {
+                return Err(AnalysisError::RegError(format!("第{}条文件分类规则没有配置任何条件", idx + 1)));
+            }
+            compiled.push(CompiledRule {
+                name,
+                content: rule.content.clone(),
+                file_type: rule.file_type.clone(),
+            });
+        }
+        Ok(FileClassifier { rules: compiled })
+    }
+
+    fn compile(pattern: &str, idx: usize) -> Result<Regex, AnalysisError> {
+        Regex::new(pattern)
+            .map_err(|e| AnalysisError::RegError(format!("第{}条文件分类规则非法:{}", idx + 1, e)))
+    }
+
+    /// 判断文件类型，文件内容只在需要嗅探时读取一次
+    pub fn classify(&self, path: &Path) -> FileType {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        self.find(file_name, || read_head(path))
+    }
+
+    /// 根据文件名和已读取的文件开头内容判断文件类型，用于压缩包中的文件
+    pub fn classify_head(&self, path: &Path, head: &[u8]) -> FileType {
+        let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        self.find(file_name, || String::from_utf8_lossy(&head[..head.len().min(SNIFF_BYTES)]).into_owned())
+    }
+
+    fn find(&self, file_name: &str, read_head: impl FnOnce() -> String) -> FileType {
+        let mut read_head = Some(read_head);
+        let mut head: Option<String> = None;
+        for rule in &self.rules {
+            if !rule.name.iter().all(|regex| regex.is_match(file_name)) {
+                continue;
+            }
+            if let Some(content) = &rule.content {
+                let head = head.get_or_insert_with(|| read_head.take().map(|read| read()).unwrap_or_default());
+                if !head.contains(content.as_str()) {
+                    continue;
+                }
+            }
+            return rule.file_type.clone();
+        }
+        FileType::None
+    }
+}
+
+impl Default for FileClassifier {
+    fn default() -> Self {
+        FileClassifier::new(&FileRule::defaults()).expect("默认文件分类规则非法")
+    }
+}
+
+/// 将glob转换为匹配整个文件名的正则表达式，支持`*`和`?`
+fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::from("^");
+    for ch in glob.chars() {
+        match ch {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+    pattern.push('$');
+    pattern
+}
+
+fn read_head(path: &Path) -> String {
+    let mut buffer = Vec::with_capacity(SNIFF_BYTES);
+    if let Ok(file) = File::open(path) {
+        let _ = file.take(SNIFF_BYTES as u64).read_to_end(&mut buffer);
+    }
+    String::from_utf8_lossy(&buffer).into_owned()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    pub fn test_classify_by_name() {
+        let classifier = FileClassifier::default();
+        let classify = |name: &str| classifier.classify(Path::new(name));
+        assert_eq!(classify("cpu_thread_1.log"), FileType::CpuThread);
+        assert_eq!(classify("cpu_top.log"), FileType::CpuTop);
+        assert_eq!(classify("threaddump_20240809_170000.log"), FileType::StackTrace);
+        assert_eq!(classify("gcutil.log"), FileType::GcUtil);
+        assert_eq!(classify("gc.log"), FileType::Gc);
+        assert_eq!(classify("missing.txt"), FileType::None);
+    }
+
+    #[test]
+    pub fn test_classify_by_content() {
+        let dir = env::temp_dir().join(format!("file_rule_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dump = dir.join("jstack.out");
+        fs::write(&dump, "2024-08-09 17:00:00\nFull thread dump OpenJDK 64-Bit Server VM:\n").unwrap();
+        let other = dir.join("notes.txt");
+        fs::write(&other, "nothing here").unwrap();
+
+        let classifier = FileClassifier::default();
+        assert_eq!(classifier.classify(&dump), FileType::StackTrace);
+        assert_eq!(classifier.classify(&other), FileType::None);
+
+        let rules = vec![FileRule {
+            glob: None,
+            regex: Some(r"\.out$".to_string()),
+            content: Some("Full thread dump".to_string()),
+            file_type: FileType::StackTrace,
+        }];
+        let classifier = FileClassifier::new(&rules).unwrap();
+        assert_eq!(classifier.classify(&dump), FileType::StackTrace);
+        assert_eq!(classifier.classify(&other), FileType::None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_invalid_rule() {
+        let rule = |regex: Option<&str>| FileRule {
+            glob: None,
+            regex: regex.map(String::from),
+            content: None,
+            file_type: FileType::Gc,
+        };
+        assert!(FileClassifier::new(&[rule(Some("("))]).is_err());
+        assert!(FileClassifier::new(&[rule(None)]).is_err());
+    }
+}
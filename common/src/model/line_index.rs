@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// 行号到字节偏移的稀疏索引，每隔`step`行记录一次行首的偏移，
+/// 读取大文件或压缩包中的指定行时可以从最近的索引点开始读取
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    pub step: usize,
+    pub offsets: Vec<u64>,
+}
+
+impl Default for LineIndex {
+    fn default() -> Self {
+        LineIndex::new(LineIndex::STEP)
+    }
+}
+
+impl LineIndex {
+    /// 默认的索引间隔行数
+    pub const STEP: usize = 1000;
+
+    pub fn new(step: usize) -> Self {
+        LineIndex { step: step.max(1), offsets: vec![] }
+    }
+
+    /// 记录第`line`行（从1开始）的行首偏移，需按行号顺序调用
+    pub fn record(&mut self, line: usize, offset: u64) {
+        if line > 0 && (line - 1).is_multiple_of(self.step) && self.offsets.len() == (line - 1) / self.step {
+            self.offsets.push(offset);
+        }
+    }
+
+    /// 不大于`line`的最近索引点，返回(行号, 偏移)
+    pub fn seek_point(&self, line: usize) -> (usize, u64) {
+        let idx = (line.max(1) - 1) / self.step;
+        match self.offsets.get(idx).or(self.offsets.last()) {
+            Some(offset) => {
+                let idx = idx.min(self.offsets.len() - 1);
+                (idx * self.step + 1, *offset)
+            }
+            None => (1, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_seek_point() {
+        let mut index = LineIndex::new(2);
+        assert_eq!(index.seek_point(5), (1, 0));
+        for (line, offset) in [(1, 0), (2, 10), (3, 25), (4, 30), (5, 42)] {
+            index.record(line, offset);
+        }
+        assert_eq!(index.offsets, vec![0, 25, 42]);
+        assert_eq!(index.seek_point(1), (1, 0));
+        assert_eq!(index.seek_point(4), (3, 25));
+        assert_eq!(index.seek_point(5), (5, 42));
+        assert_eq!(index.seek_point(100), (5, 42));
+    }
+}
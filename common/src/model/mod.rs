@@ -1 +1,3 @@
-pub mod file_info;
\ No newline at end of file
+pub mod file_info;
+pub mod file_rule;
+pub mod line_index;
@@ -1,4 +1,4 @@
-use chrono::{NaiveDateTime, NaiveTime, ParseError};
+use chrono::{Duration, NaiveDateTime, NaiveTime, ParseError};
 
 pub fn is_valid_datetime(input: &str) -> bool {
     NaiveDateTime::parse_from_str(input, "%Y-%m-%d %H:%M:%S").is_ok()
@@ -11,6 +11,15 @@ pub fn parse_time(input: &str) -> Result<NaiveTime, ParseError> {
     NaiveTime::parse_from_str(input, "%H:%M:%S")
 }
 
+/// 为只有时分秒的时间补全日期，取与`anchor`最接近的一天，跨零点时会落到前一天或后一天
+pub fn with_nearest_date(time: NaiveTime, anchor: NaiveDateTime) -> NaiveDateTime {
+    [-1, 0, 1]
+        .into_iter()
+        .map(|days| (anchor.date() + Duration::days(days)).and_time(time))
+        .min_by_key(|candidate| (*candidate - anchor).num_seconds().abs())
+        .unwrap_or_else(|| anchor.date().and_time(time))
+}
+
 pub fn parse_thread_time(input: &str) -> Result<NaiveDateTime, ParseError> {
     NaiveDateTime::parse_from_str(input, "%Y%m%d_%H%M%S")
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
 use std::{path::PathBuf, sync::{Arc, RwLock}};
 
+use common::{error::AnalysisError, model::file_rule::{FileClassifier, FileRule}};
 use serde::{Deserialize, Serialize};
 
+use crate::model::timeline::MIN_STUCK_THRESHOLD;
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
@@ -19,11 +22,64 @@ pub struct LogConfig {
     pub path: String
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnalysisConfig {
+    /// 堆栈连续多少次dump保持不变时判定为卡住的线程
+    pub stuck_threshold: usize,
+    /// 一个压缩包最多解压出的字节数，防止压缩炸弹写满磁盘
+    #[serde(default = "default_max_extract_bytes")]
+    pub max_extract_bytes: u64,
+}
+
+impl Default for AnalysisConfig {
+    fn default() -> Self {
+        AnalysisConfig { stuck_threshold: 3, max_extract_bytes: default_max_extract_bytes() }
+    }
+}
+
+fn default_max_extract_bytes() -> u64 {
+    64 * 1024 * 1024 * 1024
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FilesConfig {
+    /// 压缩包解压的根目录，每个工作空间解压到以工作空间ID命名的子目录中
+    #[serde(default = "default_data_root")]
+    pub data_root: String,
+    /// 文件分类规则，按顺序匹配，第一条命中的规则决定文件类型
+    pub rules: Vec<FileRule>,
+    /// 监听文件夹时两次扫描之间的间隔秒数
+    #[serde(default = "default_watch_interval")]
+    pub watch_interval: u64,
+}
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        FilesConfig {
+            data_root: default_data_root(),
+            rules: FileRule::defaults(),
+            watch_interval: default_watch_interval(),
+        }
+    }
+}
+
+fn default_data_root() -> String {
+    "workspace".to_string()
+}
+
+fn default_watch_interval() -> u64 {
+    5
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub log: LogConfig,
+    #[serde(default)]
+    pub analysis: AnalysisConfig,
+    #[serde(default)]
+    pub files: FilesConfig,
 }
 
 
@@ -44,11 +100,26 @@ pub struct PartialLogConfig {
     pub path: Option<String>
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PartialAnalysisConfig {
+    pub stuck_threshold: Option<usize>,
+    pub max_extract_bytes: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PartialFilesConfig {
+    pub data_root: Option<String>,
+    pub rules: Option<Vec<FileRule>>,
+    pub watch_interval: Option<u64>,
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct PartialAppConfig {
     pub server: Option<PartialServerConfig>,
     pub database: Option<PartialDatabaseConfig>,
     pub log: Option<PartialLogConfig>,
+    pub analysis: Option<PartialAnalysisConfig>,
+    pub files: Option<PartialFilesConfig>,
 }
 
 impl AppConfig {
@@ -108,6 +179,35 @@ impl AppConfig {
                     .and_then(|l| l.path.clone())
                     .unwrap_or_else(|| self.log.path.clone()),
             },
+            analysis: AnalysisConfig {
+                stuck_threshold: user
+                    .analysis
+                    .as_ref()
+                    .and_then(|a| a.stuck_threshold)
+                    .unwrap_or(self.analysis.stuck_threshold),
+                max_extract_bytes: user
+                    .analysis
+                    .as_ref()
+                    .and_then(|a| a.max_extract_bytes)
+                    .unwrap_or(self.analysis.max_extract_bytes),
+            },
+            files: FilesConfig {
+                data_root: user
+                    .files
+                    .as_ref()
+                    .and_then(|f| f.data_root.clone())
+                    .unwrap_or_else(|| self.files.data_root.clone()),
+                rules: user
+                    .files
+                    .as_ref()
+                    .and_then(|f| f.rules.clone())
+                    .unwrap_or_else(|| self.files.rules.clone()),
+                watch_interval: user
+                    .files
+                    .as_ref()
+                    .and_then(|f| f.watch_interval)
+                    .unwrap_or(self.files.watch_interval),
+            },
         }
     }
 }
@@ -129,10 +229,24 @@ impl SharedConfig {
         let user_cfg = self.user.read().unwrap();
         self.default.merge(&user_cfg)
     }
+    /// 设置用户配置，文件分类规则无法编译或卡住线程的阈值过小时拒绝整个配置
+    /// # Arguments
+    /// * `partial` - 用户配置，未设置的项使用默认配置
+    /// # Returns
+    /// * `Result<(), AnalysisError>` - 配置非法时返回InvalidParam，原有配置保持不变
     #[allow(dead_code)]
-    pub fn set_user_config(&self, partial: PartialAppConfig) {
+    pub fn set_user_config(&self, partial: PartialAppConfig) -> Result<(), AnalysisError> {
+        if let Some(rules) = partial.files.as_ref().and_then(|f| f.rules.as_ref()) {
+            FileClassifier::new(rules).map_err(|err| AnalysisError::InvalidParam(format!("文件分类规则非法:{}", err)))?;
+        }
+        if let Some(threshold) = partial.analysis.as_ref().and_then(|a| a.stuck_threshold) {
+            if threshold < MIN_STUCK_THRESHOLD {
+                return Err(AnalysisError::InvalidParam(format!("连续dump数{}不能小于{}", threshold, MIN_STUCK_THRESHOLD)));
+            }
+        }
         let mut w = self.user.write().unwrap();
         *w = partial;
+        Ok(())
     }
     #[allow(dead_code)]
     pub fn reset_user_config(&self) {
@@ -140,3 +254,41 @@ impl SharedConfig {
         *w = PartialAppConfig::default();
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use common::model::file_info::FileType;
+
+    fn shared() -> SharedConfig {
+        SharedConfig::new(AppConfig {
+            server: ServerConfig { host: "127.0.0.1".to_string(), port: 3000 },
+            database: DatabaseConfig { url: "sqlite::memory:".to_string() },
+            log: LogConfig { level: "info".to_string(), path: "log".to_string() },
+            analysis: AnalysisConfig::default(),
+            files: FilesConfig::default(),
+        })
+    }
+
+    #[test]
+    pub fn test_set_user_config() {
+        let config = shared();
+        let rule = |regex: &str| FileRule { glob: None, regex: Some(regex.to_string()), content: None, file_type: FileType::GcUtil };
+        let files = |rules| Some(PartialFilesConfig { rules: Some(rules), ..Default::default() });
+
+        let valid = PartialAppConfig { files: files(vec![rule("^gcutil.*")]), ..Default::default() };
+        assert!(config.set_user_config(valid).is_ok());
+        assert_eq!(config.get().files.rules, vec![rule("^gcutil.*")]);
+
+        // 非法的配置被拒绝，之前的配置保持不变
+        let invalid = PartialAppConfig { files: files(vec![rule("(")]), ..Default::default() };
+        assert!(matches!(config.set_user_config(invalid), Err(AnalysisError::InvalidParam(_))));
+        let threshold = PartialAppConfig {
+            analysis: Some(PartialAnalysisConfig { stuck_threshold: Some(1), ..Default::default() }),
+            ..Default::default()
+        };
+        assert!(matches!(config.set_user_config(threshold), Err(AnalysisError::InvalidParam(_))));
+        assert_eq!(config.get().files.rules, vec![rule("^gcutil.*")]);
+        assert_eq!(config.get().analysis.stuck_threshold, 3);
+    }
+}
@@ -1,86 +1,189 @@
-use chrono::NaiveTime;
+use chrono::NaiveDateTime;
 use common::string_utils::rand_id;
 use serde::Serialize;
 use sqlx::FromRow;
 use common::error::DBError;
-use sqlx::{SqlitePool, Transaction};
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::{db::db::ModelTransfer, model::cpu::Cpu};
 
 
 #[derive(Serialize, Debug, Clone, FromRow)]
 pub struct DBCpu {
+    #[sqlx(rename = "ID")]
     pub id: String,
+    #[sqlx(rename = "WORKSPACE")]
     pub workspace: String,
-    pub exe_time: NaiveTime,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: Option<String>,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: NaiveDateTime,
+    #[sqlx(rename = "LOAD1")]
+    pub load1: f64,
+    #[sqlx(rename = "LOAD5")]
+    pub load5: f64,
+    #[sqlx(rename = "LOAD15")]
+    pub load15: f64,
+    #[sqlx(rename = "US")]
     pub us: f64,
+    #[sqlx(rename = "SY")]
     pub sy: f64,
+    #[sqlx(rename = "NI")]
+    pub ni: f64,
+    #[sqlx(rename = "IDS")]
     pub ids: f64,
+    #[sqlx(rename = "WA")]
+    pub wa: f64,
+    #[sqlx(rename = "HI")]
+    pub hi: f64,
+    #[sqlx(rename = "SI")]
+    pub si: f64,
+    #[sqlx(rename = "ST")]
+    pub st: f64,
+    #[sqlx(rename = "TASKS")]
     pub tasks: u32,
+    #[sqlx(rename = "RUNNING")]
     pub running: u32,
+    #[sqlx(rename = "SLEEPING")]
     pub sleeping: u32,
+    #[sqlx(rename = "MEM_TOTAL")]
     pub mem_total: f64,
+    #[sqlx(rename = "MEM_FREE")]
     pub mem_free: f64,
+    #[sqlx(rename = "MEM_USED")]
     pub mem_used: f64,
+    #[sqlx(rename = "BUFF_CACHE")]
+    pub buff_cache: f64,
+    #[sqlx(rename = "SWAP_TOTAL")]
+    pub swap_total: f64,
+    #[sqlx(rename = "SWAP_FREE")]
+    pub swap_free: f64,
+    #[sqlx(rename = "SWAP_USED")]
+    pub swap_used: f64,
+    /// 内存和交换区字段的单位，历史数据为None，表示保存的是top表头中的原始单位
+    #[sqlx(rename = "MEM_UNIT")]
+    pub mem_unit: Option<String>,
 }
 
 
 #[derive(Serialize, Debug, Clone, FromRow)]
 pub struct DBCpuCount {
-    pub exe_time: NaiveTime,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: NaiveDateTime,
+    #[sqlx(rename = "LOAD1")]
+    pub load1: f64,
+    #[sqlx(rename = "LOAD5")]
+    pub load5: f64,
+    #[sqlx(rename = "LOAD15")]
+    pub load15: f64,
+    #[sqlx(rename = "US")]
     pub us: f64,
+    #[sqlx(rename = "SY")]
     pub sy: f64,
-    pub ids: f64
+    #[sqlx(rename = "NI")]
+    pub ni: f64,
+    #[sqlx(rename = "IDS")]
+    pub ids: f64,
+    #[sqlx(rename = "WA")]
+    pub wa: f64,
+    #[sqlx(rename = "HI")]
+    pub hi: f64,
+    #[sqlx(rename = "SI")]
+    pub si: f64,
+    #[sqlx(rename = "ST")]
+    pub st: f64,
+    #[sqlx(rename = "BUFF_CACHE")]
+    pub buff_cache: f64,
+    #[sqlx(rename = "SWAP_TOTAL")]
+    pub swap_total: f64,
+    #[sqlx(rename = "SWAP_FREE")]
+    pub swap_free: f64,
+    #[sqlx(rename = "SWAP_USED")]
+    pub swap_used: f64,
+    /// 缓存和交换区字段的单位，历史数据为None
+    #[sqlx(rename = "MEM_UNIT")]
+    pub mem_unit: Option<String>,
 }
 
 
+/// 解析时内存和交换区统一换算后的单位
+pub const MEM_UNIT_KIB: &str = "KiB";
+
 impl ModelTransfer<Cpu, DBCpu> for DBCpu {
   fn new(file: &Cpu, _file_id: &str, work_space: &str) -> DBCpu {
       DBCpu {
           id: rand_id(),
           workspace: work_space.into(),
+          file_id: Some(file.file_id.clone()),
           exe_time: file.exe_time,
+          load1: file.load1,
+          load5: file.load5,
+          load15: file.load15,
           us: file.us,
           sy: file.sy,
+          ni: file.ni,
           ids: file.ids,
+          wa: file.wa,
+          hi: file.hi,
+          si: file.si,
+          st: file.st,
           tasks: file.tasks,
           running: file.running,
           sleeping: file.sleeping,
           mem_total: file.mem_total,
           mem_free: file.mem_free,
           mem_used: file.mem_used,
+          buff_cache: file.buff_cache,
+          swap_total: file.swap_total,
+          swap_free: file.swap_free,
+          swap_used: file.swap_used,
+          mem_unit: Some(MEM_UNIT_KIB.to_string()),
       }
   }
 }
 
 
-pub async fn batch_add(pool: &SqlitePool, cpu_infos: Vec<DBCpu>) -> Result<(), DBError> {
-    let transaction: Transaction<'_, sqlx::Sqlite> = pool.begin().await?;
+pub async fn batch_add(conn: &mut SqliteConnection, cpu_infos: Vec<DBCpu>) -> Result<(), DBError> {
     for info in cpu_infos {
         sqlx::query(
-            r#"INSERT INTO CPU_INFO (id,workspace, exe_time, us, sy, ids, tasks, running, sleeping, mem_total, mem_free, mem_used)
-             VALUES (?,?,?,?,?,?,?,?,?,?,?,?) "#)
-             .bind(info.id)
-             .bind(info.workspace)
+            r#"INSERT INTO CPU_INFO (ID, WORKSPACE, FILE_ID, EXE_TIME, LOAD1, LOAD5, LOAD15, US, SY, NI, IDS, WA, HI, SI, ST, TASKS, RUNNING, SLEEPING,
+             MEM_TOTAL, MEM_FREE, MEM_USED, BUFF_CACHE, SWAP_TOTAL, SWAP_FREE, SWAP_USED, MEM_UNIT)
+             VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?) "#)
+            .bind(info.id)
+            .bind(info.workspace)
+            .bind(info.file_id)
             .bind(info.exe_time)
+            .bind(info.load1)
+            .bind(info.load5)
+            .bind(info.load15)
             .bind(info.us)
             .bind(info.sy)
+            .bind(info.ni)
             .bind(info.ids)
+            .bind(info.wa)
+            .bind(info.hi)
+            .bind(info.si)
+            .bind(info.st)
             .bind(info.tasks)
             .bind(info.running)
             .bind(info.sleeping)
             .bind(info.mem_total)
             .bind(info.mem_free)
             .bind(info.mem_used)
-            .execute(pool)
+            .bind(info.buff_cache)
+            .bind(info.swap_total)
+            .bind(info.swap_free)
+            .bind(info.swap_used)
+            .bind(info.mem_unit)
+            .execute(&mut *conn)
             .await?;
     }
-    transaction.commit().await?;
     Ok(())
 }
 
 pub async fn list(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBCpu>, DBError> {
-    let work_space = sqlx::query_as::<_, DBCpu>("SELECT * FROM CPU_INFO WHERE WORKSPACE = ? ")
+    // 历史数据的采集时间只有时分秒，重新扫描前不参与查询
+    let work_space = sqlx::query_as::<_, DBCpu>("SELECT * FROM CPU_INFO WHERE WORKSPACE = ? AND EXE_TIME LIKE '%-%'")
         .bind(work_space)
         .fetch_all(pool)
         .await?;
@@ -95,8 +198,29 @@ pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
     Ok(())
 }
 
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM CPU_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM CPU_INFO WHERE WORKSPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn count_info(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBCpuCount>, DBError> {
-    let work_space = sqlx::query_as::<_, DBCpuCount>("SELECT exe_time, us, sy, ids FROM CPU_INFO WHERE WORKSPACE = ? ")
+    // 历史数据的采集时间只有时分秒，重新扫描前不参与统计
+    let work_space = sqlx::query_as::<_, DBCpuCount>(
+        r#"SELECT EXE_TIME, LOAD1, LOAD5, LOAD15, US, SY, NI, IDS, WA, HI, SI, ST, BUFF_CACHE, SWAP_TOTAL, SWAP_FREE, SWAP_USED, MEM_UNIT
+            FROM CPU_INFO WHERE WORKSPACE = ? AND EXE_TIME LIKE '%-%' ORDER BY EXE_TIME ASC"#)
         .bind(work_space)
         .fetch_all(pool)
         .await?;
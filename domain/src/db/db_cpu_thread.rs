@@ -0,0 +1,104 @@
+use common::error::DBError;
+use common::string_utils::rand_id;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::model::cpu::CpuThread;
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBCpuThread {
+    #[sqlx(rename = "ID")]
+    pub id: String,
+    #[sqlx(rename = "WORKSPACE")]
+    pub workspace: String,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "PID")]
+    pub pid: u32,
+    #[sqlx(rename = "NID")]
+    pub nid: String,
+    #[sqlx(rename = "USER")]
+    pub user: String,
+    #[sqlx(rename = "CPU")]
+    pub cpu: f64,
+    #[sqlx(rename = "MEM")]
+    pub mem: f64,
+    #[sqlx(rename = "CPU_TIME")]
+    pub cpu_time: String,
+    #[sqlx(rename = "COMMAND")]
+    pub command: String,
+}
+
+impl DBCpuThread {
+    pub fn new(thread: &CpuThread, work_space: &str) -> Self {
+        DBCpuThread {
+            id: rand_id(),
+            workspace: work_space.into(),
+            file_id: thread.file_id.clone(),
+            pid: thread.pid,
+            nid: thread.nid.clone(),
+            user: thread.user.clone(),
+            cpu: thread.cpu,
+            mem: thread.mem,
+            cpu_time: thread.cpu_time.clone(),
+            command: thread.command.clone(),
+        }
+    }
+}
+
+pub async fn batch_add(conn: &mut SqliteConnection, cpu_threads: Vec<DBCpuThread>) -> Result<(), DBError> {
+    for cpu_thread in cpu_threads.iter() {
+        sqlx::query(
+            r#"INSERT INTO CPU_THREAD_INFO (ID, WORKSPACE, FILE_ID, PID, NID, USER, CPU, MEM, CPU_TIME, COMMAND)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(cpu_thread.id.to_owned())
+        .bind(cpu_thread.workspace.to_owned())
+        .bind(cpu_thread.file_id.to_owned())
+        .bind(cpu_thread.pid)
+        .bind(cpu_thread.nid.to_owned())
+        .bind(cpu_thread.user.to_owned())
+        .bind(cpu_thread.cpu)
+        .bind(cpu_thread.mem)
+        .bind(cpu_thread.cpu_time.to_owned())
+        .bind(cpu_thread.command.to_owned())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// 按CPU占用率降序列出工作空间中的线程
+pub async fn list_by_work_space(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBCpuThread>, DBError> {
+    let threads = sqlx::query_as::<_, DBCpuThread>("SELECT * FROM CPU_THREAD_INFO WHERE WORKSPACE = ? ORDER BY CPU DESC")
+        .bind(work_space)
+        .fetch_all(pool)
+        .await?;
+    Ok(threads)
+}
+
+pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM CPU_THREAD_INFO")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM CPU_THREAD_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM CPU_THREAD_INFO WHERE WORKSPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
@@ -1,9 +1,9 @@
 use chrono::NaiveDateTime;
-use common::model::file_info::FileInfo;
+use common::model::{file_info::FileInfo, line_index::LineIndex};
 use serde::Serialize;
 use sqlx::FromRow;
 use common::error::DBError;
-use sqlx::{SqlitePool, Transaction};
+use sqlx::{SqliteConnection, SqlitePool};
 use crate::db::{db::ModelTransfer, db_thread::DBThread};
 
 
@@ -20,6 +20,24 @@ pub struct DBSourceFile {
     pub file_type: i8,
     #[sqlx(rename = "EXE_TIME")]
     pub exe_time: Option<NaiveDateTime>,
+    /// 文件所在的压缩包，不为空时FILE_PATH为压缩包中的条目名
+    #[sqlx(rename = "ARCHIVE")]
+    pub archive: Option<String>,
+    /// 行号到字节偏移的索引，JSON格式
+    #[sqlx(rename = "LINE_INDEX")]
+    pub line_index: Option<String>,
+    #[sqlx(rename = "FILE_SIZE")]
+    pub file_size: Option<i64>,
+    /// 文件的修改时间，毫秒时间戳
+    #[sqlx(rename = "MODIFIED")]
+    pub modified: Option<i64>,
+}
+
+impl DBSourceFile {
+    pub fn with_line_index(mut self, index: Option<&LineIndex>) -> Self {
+        self.line_index = index.and_then(|index| serde_json::to_string(index).ok());
+        self
+    }
 }
 
 
@@ -31,25 +49,31 @@ impl ModelTransfer<FileInfo, DBSourceFile> for DBSourceFile{
           file_path: file.path.clone(),
           file_type: file.file_type.clone().try_into().unwrap(),
           exe_time: file.time.clone(),
+          archive: file.archive.clone(),
+          line_index: None,
+          file_size: file.size,
+          modified: file.modified,
       }
   }
 }
 
 
-pub async fn batch_add(pool: &SqlitePool, file_infos: Vec<DBSourceFile>) -> Result<(), DBError> {
-    let transaction: Transaction<'_, sqlx::Sqlite> = pool.begin().await?;
+pub async fn batch_add(conn: &mut SqliteConnection, file_infos: Vec<DBSourceFile>) -> Result<(), DBError> {
     for file_info in file_infos {
         sqlx::query(
-            r#"INSERT INTO FILE_INFO (id, workspace, file_path, file_type, exe_time) VALUES (?,?,?,?,?) "#)
+            r#"INSERT INTO FILE_INFO (id, workspace, file_path, file_type, exe_time, archive, line_index, file_size, modified) VALUES (?,?,?,?,?,?,?,?,?) "#)
             .bind(file_info.id)
             .bind(file_info.workspace)
             .bind(file_info.file_path)
             .bind(file_info.file_type)
             .bind(file_info.exe_time)
-            .execute(pool)
+            .bind(file_info.archive)
+            .bind(file_info.line_index)
+            .bind(file_info.file_size)
+            .bind(file_info.modified)
+            .execute(&mut *conn)
             .await?;    
     }
-    transaction.commit().await?;
     Ok(())
 }
 
@@ -68,8 +92,25 @@ pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
     Ok(())
 }
 
+pub async fn delete(conn: &mut SqliteConnection, id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM FILE_INFO WHERE ID = ?")
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部文件
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM FILE_INFO WHERE WORKSPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn get_file_by_thread(pool: &SqlitePool, id: &str) -> Result<DBThread, DBError> {
-    let file_info = sqlx::query_as::<_, DBThread>(r#"SELECT T.ID, F.FILE_PATH, T.THREAD_NAME, T.THREAD_STATUS, T.START_LINE, T.END_LINE FROM FILE_INFO F 
+    let file_info = sqlx::query_as::<_, DBThread>(r#"SELECT T.ID, F.FILE_PATH, F.ARCHIVE, F.LINE_INDEX, T.THREAD_NAME, T.THREAD_STATUS, T.START_LINE, T.END_LINE FROM FILE_INFO F 
                                 LEFT JOIN THREAD_INFO T 
                                 ON F.ID = T.FILE_ID 
                                 WHERE T.ID = ?"#)
@@ -77,4 +118,20 @@ pub async fn get_file_by_thread(pool: &SqlitePool, id: &str) -> Result<DBThread,
     .fetch_one(pool)
     .await?;
 Ok(file_info)
-}
\ No newline at end of file
+}
+/// 查询文件，不存在时返回None
+pub async fn find(pool: &SqlitePool, id: &str) -> Result<Option<DBSourceFile>, DBError> {
+    let file_info = sqlx::query_as::<_, DBSourceFile>("SELECT * FROM FILE_INFO WHERE ID = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(file_info)
+}
+
+pub async fn get(pool: &SqlitePool, id: &str) -> Result<DBSourceFile, DBError> {
+    let file_info = sqlx::query_as::<_, DBSourceFile>("SELECT * FROM FILE_INFO WHERE ID = ?")
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+    Ok(file_info)
+}
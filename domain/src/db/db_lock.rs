@@ -0,0 +1,114 @@
+use common::error::DBError;
+use common::string_utils::rand_id;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::{db::db_thread::DBThreadInfo, model::{lock::{format_address, LockAction}, thread::Thread}};
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBLockInfo {
+    #[sqlx(rename = "ID")]
+    pub id: String,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "THREAD_ID")]
+    pub thread_id: String,
+    #[sqlx(rename = "FRAME_INDEX")]
+    pub frame_index: i64,
+    #[sqlx(rename = "ADDRESS")]
+    pub address: String,
+    #[sqlx(rename = "ACTION")]
+    pub action: i8,
+    #[sqlx(rename = "CLASS_NAME")]
+    pub class_name: String,
+}
+
+impl DBLockInfo {
+    /// 将线程中的锁、监视器和park栈帧转换为锁记录
+    pub fn from_thread(thread: &Thread, thread_id: &str, file_id: &str) -> Vec<Self> {
+        thread
+            .frames
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, call)| {
+                let (address, action) = LockAction::from_frame(&call.frame)?;
+                Some(DBLockInfo {
+                    id: rand_id(),
+                    file_id: file_id.into(),
+                    thread_id: thread_id.into(),
+                    frame_index: idx as i64,
+                    address: format_address(address),
+                    action: action.into(),
+                    class_name: call.class_name.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+pub async fn batch_add(conn: &mut SqliteConnection, lock_infos: Vec<DBLockInfo>) -> Result<(), DBError> {
+    for lock_info in lock_infos.iter() {
+        sqlx::query(
+            r#"INSERT INTO LOCK_INFO (ID, FILE_ID, THREAD_ID, FRAME_INDEX, ADDRESS, ACTION, CLASS_NAME)
+            VALUES (?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(lock_info.id.to_owned())
+        .bind(lock_info.file_id.to_owned())
+        .bind(lock_info.thread_id.to_owned())
+        .bind(lock_info.frame_index)
+        .bind(lock_info.address.to_owned())
+        .bind(lock_info.action)
+        .bind(lock_info.class_name.to_owned())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// 查询文件中持有、等待或park在锁上的线程，按线程在dump中出现的顺序排列
+pub async fn list_lock_threads(pool: &SqlitePool, file_id: &str) -> Result<Vec<DBThreadInfo>, DBError> {
+    let threads = sqlx::query_as::<_, DBThreadInfo>(
+        r#"SELECT T.* FROM THREAD_INFO T
+            WHERE T.ID IN (SELECT L.THREAD_ID FROM LOCK_INFO L WHERE L.FILE_ID = ?)
+            ORDER BY T.START_LINE ASC"#,
+    )
+    .bind(file_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(threads)
+}
+
+pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM LOCK_INFO")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM LOCK_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除某个线程的锁记录
+pub async fn delete_by_thread(conn: &mut SqliteConnection, thread_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM LOCK_INFO WHERE THREAD_ID = ?")
+        .bind(thread_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM LOCK_INFO WHERE FILE_ID IN (SELECT ID FROM FILE_INFO WHERE WORKSPACE = ?)")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
@@ -3,74 +3,139 @@ use common::string_utils::rand_id;
 use serde::Serialize;
 use sqlx::FromRow;
 use common::error::DBError;
-use sqlx::{SqlitePool, Transaction};
+use sqlx::{SqliteConnection, SqlitePool};
 
 use crate::model::memory::MemoryValue;
 
 #[derive(Serialize, Debug, Clone, FromRow)]
 pub struct DBMemory {
+    #[sqlx(rename = "ID")]
     pub id: String,
+    #[sqlx(rename = "WORK_SPACE")]
     pub work_space: String,
+    #[sqlx(rename = "FILE_ID")]
     pub file_id: String,
+    #[sqlx(rename = "S0C")]
     pub s0c: f64,
+    #[sqlx(rename = "S0U")]
     pub s0u: f64,
+    #[sqlx(rename = "S1C")]
     pub s1c: f64,
+    #[sqlx(rename = "S1U")]
     pub s1u: f64,
+    #[sqlx(rename = "EC")]
     pub ec: f64,
+    #[sqlx(rename = "EU")]
     pub eu: f64,
+    #[sqlx(rename = "OC")]
     pub oc: f64,
+    #[sqlx(rename = "OU")]
     pub ou: f64,
+    #[sqlx(rename = "MC")]
     pub mc: f64,
+    #[sqlx(rename = "MU")]
     pub mu: f64,
+    #[sqlx(rename = "CCSC")]
     pub ccsc: f64,
+    #[sqlx(rename = "CCSU")]
     pub ccsu: f64,
+    #[sqlx(rename = "YGC")]
     pub ygc: f64,
+    #[sqlx(rename = "YGCT")]
     pub ygct: f64,
+    #[sqlx(rename = "FGC")]
     pub fgc: f64,
+    #[sqlx(rename = "FGCT")]
     pub fgct: f64,
+    #[sqlx(rename = "CGC")]
     pub cgc: f64,
+    #[sqlx(rename = "CGCT")]
     pub cgct: f64,
+    #[sqlx(rename = "GCT")]
     pub gct: f64,
+    #[sqlx(rename = "NGCMN")]
+    pub ngcmn: f64,
+    #[sqlx(rename = "NGCMX")]
+    pub ngcmx: f64,
+    #[sqlx(rename = "NGC")]
+    pub ngc: f64,
+    #[sqlx(rename = "OGCMN")]
+    pub ogcmn: f64,
+    #[sqlx(rename = "OGCMX")]
+    pub ogcmx: f64,
+    #[sqlx(rename = "OGC")]
+    pub ogc: f64,
+    #[sqlx(rename = "MCMN")]
+    pub mcmn: f64,
+    #[sqlx(rename = "MCMX")]
+    pub mcmx: f64,
+    #[sqlx(rename = "CCSMN")]
+    pub ccsmn: f64,
+    #[sqlx(rename = "CCSMX")]
+    pub ccsmx: f64,
+    /// jstat输出中无法识别的列，JSON格式
+    #[sqlx(rename = "EXTRAS")]
+    pub extras: Option<String>,
+    #[sqlx(rename = "EXE_TIME")]
     pub exe_time: Option<NaiveDateTime>,
 }
 
+/// 采样及其来源文件，历史数据中gc目录下的采样FILE_ID保存的是文件路径
+#[derive(Debug, Clone, FromRow)]
+pub struct DBMemorySource {
+    #[sqlx(flatten)]
+    pub memory: DBMemory,
+    #[sqlx(rename = "SOURCE")]
+    pub source: String,
+}
+
 impl DBMemory {
   pub fn new(memory: &MemoryValue, work_space: &str) -> Self{
       Self {
           id: rand_id(),
           work_space: work_space.into(),
           file_id: memory.file_id.clone(),
-          s0c: memory.value[0],
-          s0u: memory.value[1],
-          s1c: memory.value[2],
-          s1u: memory.value[3],
-          ec: memory.value[4],
-          eu: memory.value[5],
-          oc: memory.value[6],
-          ou: memory.value[7],
-          mc: memory.value[8],
-          mu: memory.value[9],
-          ccsc: memory.value[10],
-          ccsu: memory.value[11],
-          ygc: memory.value[12],
-          ygct: memory.value[13],
-          fgc: memory.value[14],
-          fgct: memory.value[15],
-          cgc: memory.value[16],
-          cgct: memory.value[17],
-          gct: memory.value[18],
+          s0c: memory.s0c,
+          s0u: memory.s0u,
+          s1c: memory.s1c,
+          s1u: memory.s1u,
+          ec: memory.ec,
+          eu: memory.eu,
+          oc: memory.oc,
+          ou: memory.ou,
+          mc: memory.mc,
+          mu: memory.mu,
+          ccsc: memory.ccsc,
+          ccsu: memory.ccsu,
+          ygc: memory.ygc,
+          ygct: memory.ygct,
+          fgc: memory.fgc,
+          fgct: memory.fgct,
+          cgc: memory.cgc,
+          cgct: memory.cgct,
+          gct: memory.gct,
+          ngcmn: memory.ngcmn,
+          ngcmx: memory.ngcmx,
+          ngc: memory.ngc,
+          ogcmn: memory.ogcmn,
+          ogcmx: memory.ogcmx,
+          ogc: memory.ogc,
+          mcmn: memory.mcmn,
+          mcmx: memory.mcmx,
+          ccsmn: memory.ccsmn,
+          ccsmx: memory.ccsmx,
+          extras: (!memory.extras.is_empty()).then(|| serde_json::to_string(&memory.extras).unwrap_or_default()),
           exe_time: memory.time,
       }
   }
 }
 
 
-pub async fn batch_add(pool: &SqlitePool, mem_infos: &Vec<DBMemory>) -> Result<(), DBError> {
-    let transaction: Transaction<'_, sqlx::Sqlite> = pool.begin().await?;
+pub async fn batch_add(conn: &mut SqliteConnection, mem_infos: &Vec<DBMemory>) -> Result<(), DBError> {
     for mem_info in mem_infos {
         sqlx::query(
-            r#"INSERT INTO MEMORY_INFO (ID, WORK_SPACE, FILE_ID, S0C, S0U, S1C, S1U, EC, EU, OC, OU, MC, MU, CCSC, CCSU, YGC, YGCT, FGC, FGCT, CGC, CGCT, GCT, EXE_TIME)
-             VALUES (?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)"#)
+            r#"INSERT INTO MEMORY_INFO (ID, WORK_SPACE, FILE_ID, S0C, S0U, S1C, S1U, EC, EU, OC, OU, MC, MU, CCSC, CCSU, YGC, YGCT, FGC, FGCT, CGC, CGCT, GCT, NGCMN, NGCMX, NGC, OGCMN, OGCMX, OGC, MCMN, MCMX, CCSMN, CCSMX, EXTRAS, EXE_TIME)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#)
              .bind(mem_info.id.clone())
              .bind(mem_info.work_space.clone())
             .bind(mem_info.file_id.clone())
@@ -93,25 +158,67 @@ pub async fn batch_add(pool: &SqlitePool, mem_infos: &Vec<DBMemory>) -> Result<(
             .bind(mem_info.cgc)
             .bind(mem_info.cgct)
             .bind(mem_info.gct)
+            .bind(mem_info.ngcmn)
+            .bind(mem_info.ngcmx)
+            .bind(mem_info.ngc)
+            .bind(mem_info.ogcmn)
+            .bind(mem_info.ogcmx)
+            .bind(mem_info.ogc)
+            .bind(mem_info.mcmn)
+            .bind(mem_info.mcmx)
+            .bind(mem_info.ccsmn)
+            .bind(mem_info.ccsmx)
+            .bind(mem_info.extras.clone())
             .bind(mem_info.exe_time)
-            .execute(pool)
+            .execute(&mut *conn)
             .await?;    
     }
-    transaction.commit().await?;
     Ok(())
 }
 
 pub async fn list(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBMemory>, DBError> {
-    let work_space = sqlx::query_as::<_, DBMemory>("SELECT * FROM MEMORY_INFO where WORKSPACE = ?")
+    let work_space = sqlx::query_as::<_, DBMemory>("SELECT * FROM MEMORY_INFO where WORK_SPACE = ? ORDER BY EXE_TIME")
         .bind(work_space)
         .fetch_all(pool)
         .await?;
     Ok(work_space)
 }
 
+/// 查询工作空间的采样及其来源文件路径，按采集时间升序
+pub async fn list_with_source(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBMemorySource>, DBError> {
+    let samples = sqlx::query_as::<_, DBMemorySource>(
+        r#"SELECT M.*, COALESCE(F.FILE_PATH, M.FILE_ID) AS SOURCE
+            FROM MEMORY_INFO M
+            LEFT JOIN FILE_INFO F ON F.ID = M.FILE_ID
+            WHERE M.WORK_SPACE = ?
+            ORDER BY M.EXE_TIME"#)
+        .bind(work_space)
+        .fetch_all(pool)
+        .await?;
+    Ok(samples)
+}
+
 pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
     sqlx::query("DELETE FROM MEMORY_INFO")
         .execute(pool)
         .await?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM MEMORY_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM MEMORY_INFO WHERE WORK_SPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
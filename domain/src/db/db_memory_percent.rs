@@ -0,0 +1,147 @@
+use chrono::NaiveDateTime;
+use common::error::DBError;
+use common::string_utils::rand_id;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::model::memory::MemoryPercent;
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBMemoryPercent {
+    #[sqlx(rename = "ID")]
+    pub id: String,
+    #[sqlx(rename = "WORKSPACE")]
+    pub workspace: String,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "S0")]
+    pub s0: f64,
+    #[sqlx(rename = "S1")]
+    pub s1: f64,
+    #[sqlx(rename = "E")]
+    pub e: f64,
+    #[sqlx(rename = "O")]
+    pub o: f64,
+    #[sqlx(rename = "M")]
+    pub m: f64,
+    #[sqlx(rename = "CCS")]
+    pub ccs: f64,
+    #[sqlx(rename = "YGC")]
+    pub ygc: f64,
+    #[sqlx(rename = "YGCT")]
+    pub ygct: f64,
+    #[sqlx(rename = "FGC")]
+    pub fgc: f64,
+    #[sqlx(rename = "FGCT")]
+    pub fgct: f64,
+    #[sqlx(rename = "CGC")]
+    pub cgc: f64,
+    #[sqlx(rename = "CGCT")]
+    pub cgct: f64,
+    #[sqlx(rename = "GCT")]
+    pub gct: f64,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: Option<NaiveDateTime>,
+}
+
+/// 使用率采样及其来源文件
+#[derive(Debug, Clone, FromRow)]
+pub struct DBMemoryPercentSource {
+    #[sqlx(flatten)]
+    pub percent: DBMemoryPercent,
+    #[sqlx(rename = "SOURCE")]
+    pub source: String,
+}
+
+impl DBMemoryPercent {
+    pub fn new(percent: &MemoryPercent, work_space: &str) -> Self {
+        DBMemoryPercent {
+            id: rand_id(),
+            workspace: work_space.into(),
+            file_id: percent.file_id.clone(),
+            s0: percent.s0,
+            s1: percent.s1,
+            e: percent.e,
+            o: percent.o,
+            m: percent.m,
+            ccs: percent.ccs,
+            ygc: percent.ygc,
+            ygct: percent.ygct,
+            fgc: percent.fgc,
+            fgct: percent.fgct,
+            cgc: percent.cgc,
+            cgct: percent.cgct,
+            gct: percent.gct,
+            exe_time: percent.time,
+        }
+    }
+}
+
+pub async fn batch_add(conn: &mut SqliteConnection, percents: Vec<DBMemoryPercent>) -> Result<(), DBError> {
+    for percent in percents.iter() {
+        sqlx::query(
+            r#"INSERT INTO MEMORY_PERCENT_INFO (ID, WORKSPACE, FILE_ID, S0, S1, E, O, M, CCS, YGC, YGCT, FGC, FGCT, CGC, CGCT, GCT, EXE_TIME)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(percent.id.to_owned())
+        .bind(percent.workspace.to_owned())
+        .bind(percent.file_id.to_owned())
+        .bind(percent.s0)
+        .bind(percent.s1)
+        .bind(percent.e)
+        .bind(percent.o)
+        .bind(percent.m)
+        .bind(percent.ccs)
+        .bind(percent.ygc)
+        .bind(percent.ygct)
+        .bind(percent.fgc)
+        .bind(percent.fgct)
+        .bind(percent.cgc)
+        .bind(percent.cgct)
+        .bind(percent.gct)
+        .bind(percent.exe_time)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// 按采集时间升序列出工作空间中的内存使用率及其来源文件路径，用于按JVM分组
+pub async fn list_with_source(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBMemoryPercentSource>, DBError> {
+    let percents = sqlx::query_as::<_, DBMemoryPercentSource>(
+        r#"SELECT P.*, COALESCE(F.FILE_PATH, P.FILE_ID) AS SOURCE
+            FROM MEMORY_PERCENT_INFO P
+            LEFT JOIN FILE_INFO F ON F.ID = P.FILE_ID
+            WHERE P.WORKSPACE = ?
+            ORDER BY P.EXE_TIME"#)
+        .bind(work_space)
+        .fetch_all(pool)
+        .await?;
+    Ok(percents)
+}
+
+pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM MEMORY_PERCENT_INFO")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM MEMORY_PERCENT_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM MEMORY_PERCENT_INFO WHERE WORKSPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use common::error::DBError;
+use common::string_utils::rand_id;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::{SqliteConnection, SqlitePool};
+
+use crate::model::cpu::{Cpu, Process};
+
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBProcess {
+    #[sqlx(rename = "ID")]
+    pub id: String,
+    #[sqlx(rename = "WORKSPACE")]
+    pub workspace: String,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: NaiveDateTime,
+    #[sqlx(rename = "PID")]
+    pub pid: u32,
+    #[sqlx(rename = "USER")]
+    pub user: String,
+    #[sqlx(rename = "CPU")]
+    pub cpu: f64,
+    #[sqlx(rename = "MEM")]
+    pub mem: f64,
+    #[sqlx(rename = "CPU_TIME")]
+    pub cpu_time: String,
+    #[sqlx(rename = "COMMAND")]
+    pub command: String,
+}
+
+impl DBProcess {
+    /// 将top快照中的进程表转换为进程记录
+    pub fn from_cpu(cpu: &Cpu, work_space: &str) -> Vec<Self> {
+        cpu.processes
+            .iter()
+            .map(|process| DBProcess {
+                id: rand_id(),
+                workspace: work_space.into(),
+                file_id: cpu.file_id.clone(),
+                exe_time: cpu.exe_time,
+                pid: process.pid,
+                user: process.usr.clone(),
+                cpu: process.cpu,
+                mem: process.mem,
+                cpu_time: process.time.clone(),
+                command: process.command.clone(),
+            })
+            .collect()
+    }
+
+    pub fn to_process(&self) -> Process {
+        Process {
+            pid: self.pid,
+            usr: self.user.clone(),
+            cpu: self.cpu,
+            mem: self.mem,
+            time: self.cpu_time.clone(),
+            command: self.command.clone(),
+        }
+    }
+}
+
+pub async fn batch_add(conn: &mut SqliteConnection, processes: Vec<DBProcess>) -> Result<(), DBError> {
+    for process in processes.iter() {
+        sqlx::query(
+            r#"INSERT INTO PROCESS_INFO (ID, WORKSPACE, FILE_ID, EXE_TIME, PID, USER, CPU, MEM, CPU_TIME, COMMAND)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+        )
+        .bind(process.id.to_owned())
+        .bind(process.workspace.to_owned())
+        .bind(process.file_id.to_owned())
+        .bind(process.exe_time)
+        .bind(process.pid)
+        .bind(process.user.to_owned())
+        .bind(process.cpu)
+        .bind(process.mem)
+        .bind(process.cpu_time.to_owned())
+        .bind(process.command.to_owned())
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+pub async fn list_by_work_space(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBProcess>, DBError> {
+    // 采集文件没有时间的历史数据无法确定日期，重新扫描前不参与统计
+    let processes = sqlx::query_as::<_, DBProcess>("SELECT * FROM PROCESS_INFO WHERE WORKSPACE = ? AND EXE_TIME LIKE '%-%' ORDER BY EXE_TIME ASC")
+        .bind(work_space)
+        .fetch_all(pool)
+        .await?;
+    Ok(processes)
+}
+
+pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM PROCESS_INFO")
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM PROCESS_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM PROCESS_INFO WHERE WORKSPACE = ?")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
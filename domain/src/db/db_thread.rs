@@ -1,13 +1,16 @@
+use std::{collections::HashMap, io::{self, BufRead}};
+
 use serde_json::from_str;
 
-use common::string_utils::rand_id;
+use chrono::NaiveDateTime;
+use common::{file_utils, model::line_index::LineIndex, string_utils::rand_id};
 use serde::Serialize;
 use serde_json::to_string;
 use sqlx::FromRow;
-use common::error::DBError;
-use sqlx::{SqlitePool};
+use common::error::{DBError, ThreadError};
+use sqlx::{SqliteConnection, SqlitePool};
 
-use crate::{model::thread::{CallFrame, StatusQuery, Thread, ThreadStatus}};
+use crate::{db::{db_file::{self, DBSourceFile}, db_lock::{self, DBLockInfo}}, model::{stack::FlameQuery, thread::{CallFrame, StatusQuery, Thread, ThreadStatus}}};
 
 #[derive(Serialize, Debug, Clone, FromRow)]
 pub struct DBThreadInfo {
@@ -41,6 +44,8 @@ pub struct DBThreadInfo {
     pub top_method: String,
     #[sqlx(rename = "STACK_INFO")]
     pub stack_info: String,
+    #[sqlx(rename = "STACK_FORMAT")]
+    pub stack_format: i8,
 }
 
 #[derive(Debug, Clone, FromRow)]
@@ -49,6 +54,11 @@ pub struct DBThread {
     pub id: String,
     #[sqlx(rename = "FILE_PATH")]
     pub file_path: String,
+    #[sqlx(rename = "ARCHIVE")]
+    pub archive: Option<String>,
+    /// 行号到字节偏移的索引，JSON格式
+    #[sqlx(rename = "LINE_INDEX")]
+    pub line_index: Option<String>,
     #[sqlx(rename = "THREAD_NAME")]
     pub thread_name: String,
     #[sqlx(rename = "THREAD_STATUS")]
@@ -59,6 +69,49 @@ pub struct DBThread {
     pub end_line: i64
 }
 
+impl DBThread {
+    /// 线程所在文件的行索引
+    pub fn line_index(&self) -> Option<LineIndex> {
+        self.line_index.as_deref().and_then(|index| from_str(index).ok())
+    }
+}
+
+/// 线程在某个dump文件中的采样，附带文件的采集时间
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBThreadSample {
+    #[sqlx(rename = "ID")]
+    pub id: String,
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: Option<NaiveDateTime>,
+    #[sqlx(rename = "THREAD_NAME")]
+    pub thread_name: String,
+    #[sqlx(rename = "TID")]
+    pub tid: String,
+    #[sqlx(rename = "NID")]
+    pub nid: String,
+    #[sqlx(rename = "THREAD_STATUS")]
+    pub thread_status: i8,
+    #[sqlx(rename = "TOP_METHOD")]
+    pub top_method: String,
+    #[sqlx(rename = "STACK_INFO")]
+    pub stack_info: String,
+}
+
+/// 单个dump文件中某个状态的线程数
+#[derive(Serialize, Debug, Clone, FromRow)]
+pub struct DBDumpStatus {
+    #[sqlx(rename = "FILE_ID")]
+    pub file_id: String,
+    #[sqlx(rename = "EXE_TIME")]
+    pub exe_time: Option<NaiveDateTime>,
+    #[sqlx(rename = "THREAD_STATUS")]
+    pub thread_status: i8,
+    #[sqlx(rename = "COUNT")]
+    pub count: i64,
+}
+
 #[derive(Serialize, Debug, Clone, FromRow)]
 pub struct StatusInfo {
     #[sqlx(rename = "FILE_PATH")]
@@ -69,6 +122,11 @@ pub struct StatusInfo {
     pub thread_status: i8,
 }
 
+/// 旧版本的堆栈格式，每行只保存了Frame枚举，缺少类名和方法签名
+pub const STACK_FORMAT_FRAME: i8 = 0;
+/// 每行保存一个完整的CallFrame
+pub const STACK_FORMAT_CALL_FRAME: i8 = 1;
+
 impl DBThreadInfo{
   pub fn new(thread: &Thread, file_id: &str) -> Self{
       DBThreadInfo {
@@ -86,14 +144,43 @@ impl DBThreadInfo{
           start_line: thread.start,
           end_line: thread.end,
           top_method: thread.frames.first().and_then(|frame| frame.signature.clone()).unwrap_or_default(),
-          stack_info: thread.frames.iter().map(|frame| to_string(&frame.frame).unwrap()).collect::<Vec<String>>().join("\n"),
+          stack_info: Self::stack_info(&thread.frames),
+          stack_format: STACK_FORMAT_CALL_FRAME,
       }
   }
+
+  fn stack_info(frames: &[CallFrame]) -> String {
+      frames.iter().map(|frame| to_string(frame).unwrap()).collect::<Vec<String>>().join("\n")
+  }
+
+  /// 根据源文件内容重新解析线程堆栈，用于升级旧格式的STACK_INFO，返回重新解析出的线程
+  pub fn rebuild_stack_info(&mut self, file_lines: &[String]) -> Result<Thread, ThreadError> {
+      let start = (self.start_line - 1).max(0) as usize;
+      let end = (self.end_line.max(self.start_line) as usize).min(file_lines.len());
+      if start >= end {
+          return Err(ThreadError::ParseError(format!("线程{}的行号超出文件范围", self.id)));
+      }
+      // 与解析时保持一致，线程内容在第一个空行处结束
+      let lines: Vec<String> = file_lines[start..end]
+          .iter()
+          .take_while(|line| !line.is_empty())
+          .cloned()
+          .collect();
+      let thread = Thread::new(&lines, self.start_line, self.end_line)?;
+      self.stack_info = Self::stack_info(&thread.frames);
+      self.stack_format = STACK_FORMAT_CALL_FRAME;
+      Ok(thread)
+  }
+
   pub fn to_thread(&self) -> Thread {
-        let frames: Vec<CallFrame> = self.stack_info
-            .lines()
-            .filter_map(|line| from_str(line).ok())
-            .collect();
+        // 旧格式无法还原方法签名，服务启动时会通过upgrade_stack_info升级
+        let frames: Vec<CallFrame> = match self.stack_format {
+            STACK_FORMAT_CALL_FRAME => self.stack_info
+                .lines()
+                .filter_map(|line| from_str(line).ok())
+                .collect(),
+            _ => vec![],
+        };
         Thread {
             id: self.thread_id.clone(),
             name: self.thread_name.clone(),
@@ -113,41 +200,35 @@ impl DBThreadInfo{
 
 
 pub async fn batch_add(
-    pool: &SqlitePool,
+    conn: &mut SqliteConnection,
     thread_infos: Vec<DBThreadInfo>
 ) -> Result<(), DBError> {
-    const BATCH_SIZE: usize = 1000; // 每个事务处理的最大记录数
-    for chunk in thread_infos.chunks(BATCH_SIZE) {
-        // 开始一个新的事务
-        let mut transaction = pool.begin().await?;
-
-        // 构建批量插入的 SQL 语句
-        let insert_query = String::from(
-            r#"INSERT INTO THREAD_INFO 
-            (ID, FILE_ID, THREAD_ID, THREAD_NAME, DAEMON, PRIO, OS_PRIO, TID, NID, ADDRESS,THREAD_STATUS, START_LINE, END_LINE, TOP_METHOD, STACK_INFO) 
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
-        );
-        for thread_info in chunk.iter() {
-            sqlx::query(&insert_query)
-                .bind(thread_info.id.to_owned())
-                .bind(thread_info.file_id.to_owned())
-                .bind(thread_info.thread_id.clone().unwrap_or_default())
-                .bind(thread_info.thread_name.to_owned())
-                .bind(thread_info.daemon)
-                .bind(thread_info.prio)
-                .bind(thread_info.os_prio)
-                .bind(thread_info.tid.to_owned())
-                .bind(thread_info.nid.to_owned())
-                .bind(thread_info.address.to_owned())
-                .bind(thread_info.thread_status)
-                .bind(thread_info.start_line)
-                .bind(thread_info.end_line)
-                .bind(thread_info.top_method.to_owned())
-                .bind(thread_info.stack_info.clone())
-                .execute(&mut *transaction)
-                .await?;
-        }
-        transaction.commit().await?;
+    // 构建批量插入的 SQL 语句
+    let insert_query = String::from(
+        r#"INSERT INTO THREAD_INFO 
+        (ID, FILE_ID, THREAD_ID, THREAD_NAME, DAEMON, PRIO, OS_PRIO, TID, NID, ADDRESS,THREAD_STATUS, START_LINE, END_LINE, TOP_METHOD, STACK_INFO, STACK_FORMAT) 
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"#,
+    );
+    for thread_info in thread_infos.iter() {
+        sqlx::query(&insert_query)
+            .bind(thread_info.id.to_owned())
+            .bind(thread_info.file_id.to_owned())
+            .bind(thread_info.thread_id.clone().unwrap_or_default())
+            .bind(thread_info.thread_name.to_owned())
+            .bind(thread_info.daemon)
+            .bind(thread_info.prio)
+            .bind(thread_info.os_prio)
+            .bind(thread_info.tid.to_owned())
+            .bind(thread_info.nid.to_owned())
+            .bind(thread_info.address.to_owned())
+            .bind(thread_info.thread_status)
+            .bind(thread_info.start_line)
+            .bind(thread_info.end_line)
+            .bind(thread_info.top_method.to_owned())
+            .bind(thread_info.stack_info.clone())
+            .bind(thread_info.stack_format)
+            .execute(&mut *conn)
+            .await?;
     }
     Ok(())
 }
@@ -168,6 +249,154 @@ pub async fn list_by_work_space(
     Ok(work_space)
 }
 
+/// 列出还有旧格式STACK_INFO的工作空间
+pub async fn list_legacy_work_spaces(pool: &SqlitePool) -> Result<Vec<String>, DBError> {
+    let work_spaces = sqlx::query_scalar::<_, String>(
+        r#"SELECT DISTINCT F.WORKSPACE FROM THREAD_INFO T
+            JOIN FILE_INFO F ON T.FILE_ID = F.ID
+            WHERE T.STACK_FORMAT <> ?"#)
+        .bind(STACK_FORMAT_CALL_FRAME)
+        .fetch_all(pool)
+        .await?;
+    Ok(work_spaces)
+}
+
+/// 将工作空间中旧格式的STACK_INFO从源文件重新解析为完整的CallFrame，同时重建这些线程的LOCK_INFO
+/// # Returns
+/// * `Result<usize, DBError>` - 返回升级的线程数量
+pub async fn upgrade_stack_info(pool: &SqlitePool, work_space_id: &str) -> Result<usize, DBError> {
+    let legacy: Vec<DBThreadInfo> = list_by_work_space(pool, work_space_id)
+        .await?
+        .into_iter()
+        .filter(|info| info.stack_format != STACK_FORMAT_CALL_FRAME)
+        .collect();
+    if legacy.is_empty() {
+        return Ok(0);
+    }
+    let files: HashMap<String, DBSourceFile> = db_file::list(pool, work_space_id)
+        .await?
+        .into_iter()
+        .map(|file| (file.id.clone(), file))
+        .collect();
+    let mut file_threads: HashMap<String, Vec<DBThreadInfo>> = HashMap::new();
+    for info in legacy {
+        file_threads.entry(info.file_id.clone()).or_default().push(info);
+    }
+    let mut upgraded: Vec<(DBThreadInfo, Vec<DBLockInfo>)> = Vec::new();
+    let readers = file_utils::ArchiveReaders::default();
+    for (file_id, threads) in file_threads {
+        let Some(file) = files.get(&file_id) else {
+            continue;
+        };
+        let file_lines = readers.with_reader(&file.file_path, file.archive.as_deref(), |reader| {
+            reader.lines().collect::<io::Result<Vec<String>>>()
+        });
+        let file_lines: Vec<String> = match file_lines {
+            Ok(lines) => lines,
+            Err(err) => {
+                log::warn!("读取堆栈文件{}失败，跳过升级:{}", file.file_path, err);
+                continue;
+            }
+        };
+        for mut thread in threads {
+            match thread.rebuild_stack_info(&file_lines) {
+                Ok(rebuilt) => {
+                    // 导入时还没有LOCK_INFO的工作空间需要补全锁信息
+                    let locks = DBLockInfo::from_thread(&rebuilt, &thread.id, &thread.file_id);
+                    upgraded.push((thread, locks));
+                }
+                Err(err) => log::warn!("升级线程{}的堆栈失败:{}", thread.id, err),
+            }
+        }
+    }
+    const BATCH_SIZE: usize = 1000;
+    for chunk in upgraded.chunks(BATCH_SIZE) {
+        let mut transaction = pool.begin().await?;
+        for (thread, locks) in chunk {
+            sqlx::query("UPDATE THREAD_INFO SET STACK_INFO = ?, STACK_FORMAT = ? WHERE ID = ?")
+                .bind(thread.stack_info.clone())
+                .bind(thread.stack_format)
+                .bind(thread.id.clone())
+                .execute(&mut *transaction)
+                .await?;
+            db_lock::delete_by_thread(&mut transaction, &thread.id).await?;
+            db_lock::batch_add(&mut transaction, locks.clone()).await?;
+        }
+        transaction.commit().await?;
+    }
+    Ok(upgraded.len())
+}
+
+/// 按采集时间顺序列出工作空间中的线程采样，可按线程名、nid、tid过滤
+pub async fn list_samples(
+    pool: &SqlitePool,
+    work_space_id: &str,
+    name: &Option<String>,
+    nid: &Option<String>,
+    tid: &Option<String>,
+) -> Result<Vec<DBThreadSample>, DBError> {
+    let mut sql = r#"SELECT T.ID, T.FILE_ID, F.EXE_TIME, T.THREAD_NAME, T.TID, T.NID, T.THREAD_STATUS, T.TOP_METHOD, T.STACK_INFO
+        FROM THREAD_INFO T
+        LEFT JOIN FILE_INFO F
+        ON T.FILE_ID = F.ID
+        WHERE F.WORKSPACE = ?"#.to_string();
+    let filters: Vec<(&str, &String)> = [("T.THREAD_NAME", name), ("T.NID", nid), ("T.TID", tid)]
+        .into_iter()
+        .filter_map(|(column, value)| value.as_ref().map(|v| (column, v)))
+        .collect();
+    for (column, _) in &filters {
+        sql.push_str(&format!(" AND {} = ?", column));
+    }
+    sql.push_str(" ORDER BY F.EXE_TIME ASC");
+    let mut query_builder = sqlx::query_as::<_, DBThreadSample>(&sql).bind(work_space_id);
+    for (_, value) in filters {
+        query_builder = query_builder.bind(value);
+    }
+    let result = query_builder.fetch_all(pool).await?;
+    Ok(result)
+}
+
+/// 按线程状态、线程名前缀和采集时间范围列出工作空间中的线程
+pub async fn list_by_filter(
+    pool: &SqlitePool,
+    work_space_id: &str,
+    query: &FlameQuery,
+) -> Result<Vec<DBThreadInfo>, DBError> {
+    let mut sql = r#"SELECT T.* FROM THREAD_INFO T
+        LEFT JOIN FILE_INFO F
+        ON T.FILE_ID = F.ID
+        WHERE F.WORKSPACE = ?"#.to_string();
+    let prefix = query.prefix.as_ref().filter(|prefix| !prefix.is_empty());
+    if query.status.is_some() {
+        sql.push_str(" AND T.THREAD_STATUS = ?");
+    }
+    if prefix.is_some() {
+        // LIKE会把线程名中的'_'当作通配符，这里按前缀精确比较
+        sql.push_str(" AND SUBSTR(T.THREAD_NAME, 1, LENGTH(?)) = ?");
+    }
+    if query.start_time.is_some() {
+        sql.push_str(" AND F.EXE_TIME >= ?");
+    }
+    if query.end_time.is_some() {
+        sql.push_str(" AND F.EXE_TIME <= ?");
+    }
+    let mut query_builder = sqlx::query_as::<_, DBThreadInfo>(&sql).bind(work_space_id);
+    if let Some(status) = &query.status {
+        query_builder = query_builder.bind(i8::from(status.clone()));
+    }
+    if let Some(prefix) = prefix {
+        query_builder = query_builder.bind(prefix).bind(prefix);
+    }
+    if let Some(start_time) = query.start_time {
+        query_builder = query_builder.bind(start_time);
+    }
+    if let Some(end_time) = query.end_time {
+        query_builder = query_builder.bind(end_time);
+    }
+    let result = query_builder.fetch_all(pool).await?;
+    Ok(result)
+}
+
 pub async fn get(pool: &SqlitePool, id: &str) -> Result<DBThreadInfo, DBError> {
     let work_sapce = sqlx::query_as::<_, DBThreadInfo>("SELECT * FROM THREAD_INFO WHERE ID = ?")
         .bind(id)
@@ -191,6 +420,39 @@ pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
     Ok(())
 }
 
+/// 删除某个文件解析出的数据
+pub async fn delete_by_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM THREAD_INFO WHERE FILE_ID = ?")
+        .bind(file_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部数据
+pub async fn delete_by_workspace(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), DBError> {
+    sqlx::query("DELETE FROM THREAD_INFO WHERE FILE_ID IN (SELECT ID FROM FILE_INFO WHERE WORKSPACE = ?)")
+        .bind(work_space_id)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// 统计工作空间中每个dump文件各状态的线程数
+pub async fn count_status_by_dump(pool: &SqlitePool, work_space: &str) -> Result<Vec<DBDumpStatus>, DBError> {
+    let result = sqlx::query_as::<_, DBDumpStatus>(
+        r#"SELECT T.FILE_ID, F.EXE_TIME, T.THREAD_STATUS, COUNT(*) AS COUNT FROM THREAD_INFO T
+            JOIN FILE_INFO F ON T.FILE_ID = F.ID
+            WHERE F.WORKSPACE = ?
+            GROUP BY T.FILE_ID, F.EXE_TIME, T.THREAD_STATUS
+            ORDER BY F.EXE_TIME ASC"#,
+    )
+    .bind(work_space)
+    .fetch_all(pool)
+    .await?;
+    Ok(result)
+}
+
 pub async fn count_threads_status(
     pool: &SqlitePool,
     _status: &StatusQuery,
@@ -3,7 +3,7 @@ use common::error::DBError;
 use common::string_utils::rand_id;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 #[derive(Serialize, Deserialize, Debug, Clone, FromRow)]
 pub struct DBFileWorkSpace {
@@ -15,6 +15,9 @@ pub struct DBFileWorkSpace {
     pub create_time: NaiveDateTime,
     #[sqlx(rename = "UPDATE_TIME")]
     pub update_time: NaiveDateTime,
+    /// 压缩包解压到的目录，直接打开文件夹时为None
+    #[sqlx(rename = "EXTRACT_ROOT")]
+    pub extract_root: Option<String>,
 }
 
 impl DBFileWorkSpace {
@@ -24,15 +27,17 @@ impl DBFileWorkSpace {
             file_path: path.into(),
             create_time: Utc::now().naive_utc(),
             update_time: Utc::now().naive_utc(),
+            extract_root: None,
         }
     }
 }
 
-pub async fn add(pool: &SqlitePool, work_space: &DBFileWorkSpace) -> Result<(), DBError> {
-    sqlx::query(r#"INSERT INTO FILE_WORKSPACE (ID, file_path) VALUES (?,?) "#)
+pub async fn add(conn: &mut SqliteConnection, work_space: &DBFileWorkSpace) -> Result<(), DBError> {
+    sqlx::query(r#"INSERT INTO FILE_WORKSPACE (ID, file_path, EXTRACT_ROOT) VALUES (?,?,?) "#)
         .bind(work_space.id.to_string())
         .bind(work_space.file_path.to_string())
-        .execute(pool)
+        .bind(work_space.extract_root.as_deref())
+        .execute(&mut *conn)
         .await?;
     Ok(())
 }
@@ -79,8 +84,8 @@ pub async fn delete_all(pool: &SqlitePool) -> Result<(), DBError> {
         .await?;
     Ok(())
 }
-pub async fn update_time(pool: &SqlitePool, id: i32, time: NaiveDateTime) -> Result<(), DBError> {
-    sqlx::query("UPDATE FILE_WORKSPACE SET update_time = $1 WHERE ID = $2")
+pub async fn update_time(pool: &SqlitePool, id: &str, time: NaiveDateTime) -> Result<(), DBError> {
+    sqlx::query("UPDATE FILE_WORKSPACE SET UPDATE_TIME = $1 WHERE ID = $2")
         .bind(time)
         .bind(id)
         .execute(pool)
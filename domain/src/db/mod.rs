@@ -1,6 +1,10 @@
 pub mod db_cpu;
+pub mod db_cpu_thread;
 pub mod db_file;
+pub mod db_lock;
 pub mod db_memory;
+pub mod db_memory_percent;
+pub mod db_process;
 pub mod db_thread;
 pub mod db_workspace;
 pub mod db;
\ No newline at end of file
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{Duration, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    db::{db_cpu::DBCpuCount, db_memory::DBMemorySource, db_thread::DBDumpStatus},
+    model::{memory::series_key, thread::ThreadStatus},
+};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CorrelatedQuery {
+    /// 对齐的时间粒度（秒），同一区间内的采样取平均值，默认不合并
+    pub interval: Option<i64>,
+}
+
+/// CPU、堆内存和线程状态在同一时间轴上的序列，某个时间点没有采样时为None
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CorrelatedTimeline {
+    pub time: Vec<NaiveDateTime>,
+    pub us: Vec<Option<f64>>,
+    pub sy: Vec<Option<f64>>,
+    pub id: Vec<Option<f64>>,
+    /// 每个JVM一组堆内存序列，不同JVM的堆内存不能相加或取平均
+    pub heap: Vec<HeapSeries>,
+    pub runnable: Vec<Option<f64>>,
+    pub blocked: Vec<Option<f64>>,
+    pub waiting: Vec<Option<f64>>,
+    pub timed_waiting: Vec<Option<f64>>,
+}
+
+/// 单个JVM在共享时间轴上的堆内存序列，单位为KB
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HeapSeries {
+    /// 文件名中的进程号，文件名中没有数字时为文件名
+    pub jvm: String,
+    pub used: Vec<Option<f64>>,
+    pub capacity: Vec<Option<f64>>,
+}
+
+/// 按时间区间累计各序列的采样值
+struct SeriesBuilder {
+    interval: i64,
+    points: BTreeMap<NaiveDateTime, HashMap<String, (f64, usize)>>,
+}
+
+impl SeriesBuilder {
+    fn add(&mut self, time: NaiveDateTime, name: &str, value: f64) {
+        let time = if self.interval > 1 {
+            let seconds = time.and_utc().timestamp();
+            time - Duration::seconds(seconds.rem_euclid(self.interval))
+        } else {
+            time
+        };
+        let (sum, count) = self.points.entry(time).or_default().entry(name.to_string()).or_default();
+        *sum += value;
+        *count += 1;
+    }
+
+    fn series(&self, name: &str) -> Vec<Option<f64>> {
+        self.points
+            .values()
+            .map(|values| values.get(name).map(|(sum, count)| sum / *count as f64))
+            .collect()
+    }
+}
+
+impl CorrelatedTimeline {
+    /// 合并CPU、jstat和jstack的采样，没有采集时间的采样会被忽略，jstat采样按来源文件中的进程号分为不同JVM的序列
+    pub fn new(
+        interval: i64,
+        cpu: &[DBCpuCount],
+        memory: &[DBMemorySource],
+        threads: &[DBDumpStatus],
+    ) -> CorrelatedTimeline {
+        let mut builder = SeriesBuilder { interval, points: BTreeMap::new() };
+        for sample in cpu {
+            builder.add(sample.exe_time, "us", sample.us);
+            builder.add(sample.exe_time, "sy", sample.sy);
+            builder.add(sample.exe_time, "id", sample.ids);
+        }
+        let mut jvms = BTreeSet::new();
+        for DBMemorySource { memory: sample, source } in memory {
+            let Some(time) = sample.exe_time else {
+                continue;
+            };
+            let (jvm, _) = series_key(source);
+            builder.add(time, &format!("heap_used:{}", jvm), sample.s0u + sample.s1u + sample.eu + sample.ou);
+            builder.add(time, &format!("heap_capacity:{}", jvm), sample.s0c + sample.s1c + sample.ec + sample.oc);
+            jvms.insert(jvm);
+        }
+        // 同一个dump的各状态先汇总，保证每个dump在所有状态序列中都有值
+        let mut dumps: BTreeMap<(NaiveDateTime, &str), [f64; 4]> = BTreeMap::new();
+        for status in threads {
+            let Some(time) = status.exe_time else {
+                continue;
+            };
+            let counts = dumps.entry((time, status.file_id.as_str())).or_default();
+            match ThreadStatus::try_from(status.thread_status) {
+                Ok(ThreadStatus::Runnable) => counts[0] += status.count as f64,
+                Ok(ThreadStatus::Blocked) => counts[1] += status.count as f64,
+                Ok(ThreadStatus::Waiting) => counts[2] += status.count as f64,
+                Ok(ThreadStatus::TimedWaiting) => counts[3] += status.count as f64,
+                _ => {}
+            }
+        }
+        for ((time, _), counts) in dumps {
+            builder.add(time, "runnable", counts[0]);
+            builder.add(time, "blocked", counts[1]);
+            builder.add(time, "waiting", counts[2]);
+            builder.add(time, "timed_waiting", counts[3]);
+        }
+        CorrelatedTimeline {
+            time: builder.points.keys().copied().collect(),
+            us: builder.series("us"),
+            sy: builder.series("sy"),
+            id: builder.series("id"),
+            heap: jvms
+                .into_iter()
+                .map(|jvm| HeapSeries {
+                    used: builder.series(&format!("heap_used:{}", jvm)),
+                    capacity: builder.series(&format!("heap_capacity:{}", jvm)),
+                    jvm,
+                })
+                .collect(),
+            runnable: builder.series("runnable"),
+            blocked: builder.series("blocked"),
+            waiting: builder.series("waiting"),
+            timed_waiting: builder.series("timed_waiting"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::db::db_memory::{DBMemory, DBMemorySource};
+    use crate::model::memory::MemoryValue;
+
+    fn time(minute: u32, second: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 8, 9)
+            .and_then(|d| d.and_hms_opt(17, minute, second))
+            .unwrap()
+    }
+
+    #[test]
+    pub fn test_correlated_timeline() {
+        let status = |file: &str, minute: u32, status: ThreadStatus, count: i64| DBDumpStatus {
+            file_id: file.to_string(),
+            exe_time: Some(time(minute, 0)),
+            thread_status: status.into(),
+            count,
+        };
+        let threads = vec![
+            status("a", 1, ThreadStatus::Runnable, 10),
+            status("a", 1, ThreadStatus::Blocked, 2),
+            status("b", 2, ThreadStatus::Waiting, 5),
+        ];
+        let sample = |source: &str, eu: f64, ou: f64| DBMemorySource {
+            memory: DBMemory::new(
+                &MemoryValue { time: Some(time(2, 20)), eu, ou, ec: 200.0, oc: 300.0, ..Default::default() },
+                "w",
+            ),
+            source: source.to_string(),
+        };
+        // 两个JVM在同一时间点的采样分别成为独立的序列，不会被平均
+        let memory = vec![sample("cap/gc/gc_100.log", 100.0, 50.0), sample("cap/gc/gc_200.log", 10.0, 5.0)];
+        let timeline = CorrelatedTimeline::new(60, &[], &memory, &threads);
+        assert_eq!(timeline.time, vec![time(1, 0), time(2, 0)]);
+        assert_eq!(timeline.runnable, vec![Some(10.0), Some(0.0)]);
+        assert_eq!(timeline.blocked, vec![Some(2.0), Some(0.0)]);
+        assert_eq!(timeline.waiting, vec![Some(0.0), Some(5.0)]);
+        assert_eq!(timeline.heap.len(), 2);
+        assert_eq!(timeline.heap[0].jvm, "100");
+        assert_eq!(timeline.heap[0].used, vec![None, Some(150.0)]);
+        assert_eq!(timeline.heap[0].capacity, vec![None, Some(500.0)]);
+        assert_eq!((timeline.heap[1].jvm.as_str(), timeline.heap[1].used.clone()), ("200", vec![None, Some(15.0)]));
+        assert_eq!(timeline.us, vec![None, None]);
+
+        let unaligned = CorrelatedTimeline::new(0, &[], &memory, &threads);
+        assert_eq!(unaligned.time.len(), 3);
+    }
+}
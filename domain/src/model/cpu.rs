@@ -1,139 +1,242 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
-use chrono::{NaiveTime};
+use chrono::NaiveDateTime;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use common::time_utils;
+use common::{error::LineError, time_utils};
+
+use crate::model::thread::ThreadStatus;
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Cpu {
     pub file_id: String,
     pub work_space: String,
-    pub exe_time: NaiveTime,
+    pub exe_time: NaiveDateTime,
+    pub load1: f64,
+    pub load5: f64,
+    pub load15: f64,
     pub us: f64,
     pub sy: f64,
+    pub ni: f64,
     pub ids: f64,
+    pub wa: f64,
+    pub hi: f64,
+    pub si: f64,
+    pub st: f64,
     pub tasks: u32,
     pub running: u32,
     pub sleeping: u32,
+    /// 内存和交换区的单位统一为KiB
     pub mem_total: f64,
     pub mem_free: f64,
     pub mem_used: f64,
+    pub buff_cache: f64,
+    pub swap_total: f64,
+    pub swap_free: f64,
+    pub swap_used: f64,
+    pub processes: Vec<Process>,
+}
+
+lazy_static::lazy_static! {
+    static ref REGEX_TOP_TIME:Regex = Regex::new(r"^top\s+-\s+(\d{1,2}:\d{2}:\d{2})").unwrap();
+    static ref REGEX_LOAD:Regex = Regex::new(r"(?i)load average:\s*([\d.]+),?\s+([\d.]+),?\s+([\d.]+)(?:\s+(\d+)/(\d+))?").unwrap();
+    // 数值 + 可选的单位后缀 + 名称，兼容"12.5 us"、"12.5%us"、"2% usr"、"1922420K used"
+    static ref REGEX_VALUE:Regex = Regex::new(r"([\d.]+)\s*%?([kKmMgG])?\s*([A-Za-z/]+)").unwrap();
 }
 
 impl Cpu {
-    pub fn new(lines: Vec<String>, file_id: &str, work_space: &str) -> Self {
-        let time = Self::extract_main(lines.get(0).unwrap());
-        let (tasks, running, sleeping) = Self::extract_threads(lines.get(1).unwrap());
-        let (us, sy, id) = Self::extract_cpu(lines.get(2).unwrap());
-        let (mem_total, mem_free, mem_used) = Self::extract_mem(lines.get(3).unwrap());
-        // let process: Vec<Process> = Vec::with_capacity(0);
-        // for i in 7..lines.len() {
-        //     process.push(Process::from_str(&lines[i]).unwrap());
-        // }
-        Cpu {
+    /// 解析top快照，兼容procps-ng（含旧版procps）和BusyBox的表头格式。
+    /// top表头只有时分秒，日期取自`file_time`（采集目录名或文件修改时间），BusyBox的输出中没有采集时间，此时直接使用`file_time`。
+    /// 缺少CPU使用率行、无法确定采集时间或表头数值无法解析时返回出错的行号和原因
+    pub fn new(lines: Vec<String>, file_id: &str, work_space: &str, file_time: Option<NaiveDateTime>) -> Result<Self, LineError> {
+        let mut cpu = Cpu {
             file_id: file_id.into(),
             work_space: work_space.into(),
-            exe_time: time_utils::parse_time(&time).unwrap(),
-            us,
-            sy,
-            ids:id,
-            tasks,
-            running,
-            sleeping,
-            mem_total,
-            mem_free,
-            mem_used,
+            exe_time: NaiveDateTime::default(),
+            load1: 0.0,
+            load5: 0.0,
+            load15: 0.0,
+            us: 0.0,
+            sy: 0.0,
+            ni: 0.0,
+            ids: 0.0,
+            wa: 0.0,
+            hi: 0.0,
+            si: 0.0,
+            st: 0.0,
+            tasks: 0,
+            running: 0,
+            sleeping: 0,
+            mem_total: 0.0,
+            mem_free: 0.0,
+            mem_used: 0.0,
+            buff_cache: 0.0,
+            swap_total: 0.0,
+            swap_free: 0.0,
+            swap_used: 0.0,
+            processes: Process::parse_table(&lines),
+        };
+        let mut exe_time = file_time;
+        let mut has_cpu = false;
+        // 表头在进程表之前，遇到PID列标题即结束
+        for (idx, line) in lines.iter().enumerate().take_while(|(_, line)| !line.trim_start().starts_with("PID")) {
+            let line_no = Some(idx + 1);
+            let line = line.trim();
+            if let Some(caps) = REGEX_TOP_TIME.captures(line) {
+                let time = time_utils::parse_time(&caps[1])
+                    .map_err(|_| LineError::new(line_no, format!("无法解析采集时间:{}", &caps[1])))?;
+                let anchor = file_time.ok_or_else(|| LineError::new(line_no, "无法确定采集日期"))?;
+                exe_time = Some(time_utils::with_nearest_date(time, anchor));
+            }
+            if line.to_ascii_lowercase().contains("load average") {
+                let caps = REGEX_LOAD
+                    .captures(line)
+                    .ok_or_else(|| LineError::new(line_no, "无法解析系统负载"))?;
+                let load = |idx: usize| {
+                    caps[idx]
+                        .parse::<f64>()
+                        .map_err(|_| LineError::new(line_no, format!("无法解析系统负载:{}", &caps[idx])))
+                };
+                cpu.load1 = load(1)?;
+                cpu.load5 = load(2)?;
+                cpu.load15 = load(3)?;
+                // BusyBox在负载后给出"运行数/总数"
+                if let (Some(running), Some(total)) = (caps.get(4), caps.get(5)) {
+                    cpu.running = running.as_str().parse().unwrap_or_default();
+                    cpu.tasks = total.as_str().parse().unwrap_or_default();
+                    cpu.sleeping = cpu.tasks.saturating_sub(cpu.running);
+                }
+            }
+            let Some((title, values)) = line.split_once(':') else {
+                continue;
+            };
+            let parsed = match title.trim() {
+                "Tasks" | "Threads" => cpu.extract_tasks(values),
+                "%Cpu(s)" | "Cpu(s)" | "CPU" => {
+                    has_cpu = true;
+                    cpu.extract_cpu(values)
+                }
+                "Mem" | "KiB Mem" | "MiB Mem" | "GiB Mem" | "TiB Mem" => cpu.extract_mem(title, values),
+                "Swap" | "KiB Swap" | "MiB Swap" | "GiB Swap" | "TiB Swap" => cpu.extract_swap(title, values),
+                _ => true,
+            };
+            if !parsed {
+                return Err(LineError::new(line_no, format!("无法解析{}行", title.trim())));
+            }
+        }
+        if !has_cpu {
+            return Err(LineError::new(None, "未找到CPU使用率行"));
+        }
+        cpu.exe_time = exe_time.ok_or_else(|| LineError::new(None, "无法确定采集时间"))?;
+        Ok(cpu)
+    }
+
+    fn values(line: &str) -> impl Iterator<Item = (f64, Option<char>, String)> + '_ {
+        REGEX_VALUE.captures_iter(line).filter_map(|caps| {
+            let value = caps[1].parse::<f64>().ok()?;
+            let suffix = caps.get(2).and_then(|m| m.as_str().chars().next());
+            Some((value, suffix, caps[3].to_string()))
+        })
+    }
+
+    /// 将数值换算为KiB，数值自带的单位后缀优先于行首的单位
+    fn to_kib(title: &str, value: f64, suffix: Option<char>) -> f64 {
+        let unit = match suffix {
+            Some(suffix) => suffix.to_ascii_uppercase(),
+            // 只有"KiB Mem"这类带单位的标题才取首字母，"Mem"本身不是单位
+            None => title
+                .trim()
+                .split_once(' ')
+                .and_then(|(unit, _)| unit.chars().next())
+                .unwrap_or('K'),
+        };
+        match unit {
+            'M' => value * 1024.0,
+            'G' => value * 1024.0 * 1024.0,
+            'T' => value * 1024.0 * 1024.0 * 1024.0,
+            _ => value,
         }
     }
-    fn extract_main(line: &str) -> String {
-        let infos: Vec<&str> = line.split(",").collect();
-        let cpu_running: Vec<&str> = infos[0].split_whitespace().collect();
-        cpu_running[2].to_string()
-    }
-
-    fn extract_threads(line: &str) -> (u32, u32, u32) {
-        let tasks: Vec<&str> = line.split(",").collect();
-        let total_info: Vec<&str> = tasks[0].split_whitespace().collect();
-        let running_info: Vec<&str> = tasks[1].split_whitespace().collect();
-        let sleep_info: Vec<&str> = tasks[2].split_whitespace().collect();
-
-        let total = total_info.get(1).unwrap().parse::<u32>().unwrap();
-        let running = running_info.get(0).unwrap().parse::<u32>().unwrap();
-        let sleep = sleep_info.get(0).unwrap().parse::<u32>().unwrap();
-        (total, running, sleep)
-    }
-
-    fn extract_cpu(line: &str) -> (f64, f64, f64) {
-        let cpu_line = line.split(":").nth(1).unwrap();
-        let cpu_vec: Vec<&str> = cpu_line.split(",").collect();
-        let us = cpu_vec
-            .get(0)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .parse::<f64>()
-            .unwrap();
-        let sy = cpu_vec
-            .get(1)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .parse::<f64>()
-            .unwrap();
-        let id = cpu_vec
-            .get(3)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .parse::<f64>()
-            .unwrap();
-        (us, sy, id)
-    }
-    fn extract_mem(line: &str) -> (f64, f64, f64) {
-        let mem_line = line.split(":").nth(1).unwrap();
-        let mem_vec: Vec<&str> = mem_line.split(",").collect();
-        let total = mem_vec
-            .get(0)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .trim()
-            .parse::<f64>()
-            .unwrap();
-        let free = mem_vec
-            .get(1)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .trim()
-            .parse::<f64>()
-            .unwrap();
-        let used = mem_vec
-            .get(2)
-            .unwrap()
-            .split_whitespace()
-            .next()
-            .unwrap()
-            .trim()
-            .parse::<f64>()
-            .unwrap();
-        (total, free, used)
+
+    /// 以下extract方法在一个已知字段都没有识别到时返回false
+    fn extract_tasks(&mut self, line: &str) -> bool {
+        let mut parsed = false;
+        for (value, _, name) in Self::values(line) {
+            match name.as_str() {
+                "total" => self.tasks = value as u32,
+                "running" => self.running = value as u32,
+                "sleeping" => self.sleeping = value as u32,
+                _ => continue,
+            }
+            parsed = true;
+        }
+        parsed
+    }
+
+    fn extract_cpu(&mut self, line: &str) -> bool {
+        let mut parsed = false;
+        for (value, _, name) in Self::values(line) {
+            match name.as_str() {
+                "us" | "usr" => self.us = value,
+                "sy" | "sys" => self.sy = value,
+                "ni" | "nic" => self.ni = value,
+                "id" | "idle" => self.ids = value,
+                "wa" | "io" => self.wa = value,
+                "hi" | "irq" => self.hi = value,
+                "si" | "sirq" => self.si = value,
+                "st" => self.st = value,
+                _ => continue,
+            }
+            parsed = true;
+        }
+        parsed
+    }
+
+    fn extract_mem(&mut self, title: &str, line: &str) -> bool {
+        let mut parsed = false;
+        let mut total = None;
+        for (value, suffix, name) in Self::values(line) {
+            let value = Self::to_kib(title, value, suffix);
+            match name.as_str() {
+                "total" => total = Some(value),
+                "free" => self.mem_free = value,
+                "used" => self.mem_used = value,
+                // procps-ng为buff/cache，旧版procps为buffers（cached在Swap行），BusyBox为buff和cached
+                "buff/cache" | "buffers" | "buff" | "cached" => self.buff_cache += value,
+                _ => continue,
+            }
+            parsed = true;
+        }
+        // BusyBox没有total，由used和free相加得到
+        self.mem_total = total.unwrap_or(self.mem_used + self.mem_free);
+        parsed
+    }
+
+    fn extract_swap(&mut self, title: &str, line: &str) -> bool {
+        let mut parsed = false;
+        for (value, suffix, name) in Self::values(line) {
+            let value = Self::to_kib(title, value, suffix);
+            match name.as_str() {
+                "total" => self.swap_total = value,
+                "free" => self.swap_free = value,
+                "used" => self.swap_used = value,
+                "cached" => self.buff_cache += value,
+                _ => continue,
+            }
+            parsed = true;
+        }
+        parsed
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct Process {
-    pid: u32,
-    usr: String,
-    cpu: f64,
-    mem: f64,
-    time: String,
-    command: String,
+    pub pid: u32,
+    pub usr: String,
+    pub cpu: f64,
+    pub mem: f64,
+    pub time: String,
+    pub command: String,
 }
 
 impl FromStr for Process {
@@ -152,17 +255,198 @@ impl FromStr for Process {
 }
 
 
+impl Process {
+    /// 解析top的任务表，从表头（以PID开头的行）之后开始，列位置根据表头确定，无法识别的行会被跳过
+    pub fn parse_table(lines: &[String]) -> Vec<Process> {
+        let Some(header_idx) = lines.iter().position(|line| line.trim_start().starts_with("PID")) else {
+            return vec![];
+        };
+        let header: Vec<&str> = lines[header_idx].split_whitespace().collect();
+        let column = |name: &str| header.iter().position(|h| *h == name);
+        // BusyBox的进程表没有%MEM和TIME+，内存占比使用%VSZ
+        let (Some(pid), Some(cpu), Some(mem), Some(command)) =
+            (column("PID"), column("%CPU"), column("%MEM").or(column("%VSZ")), column("COMMAND"))
+        else {
+            return vec![];
+        };
+        let user = column("USER");
+        let time = column("TIME+");
+        lines[header_idx + 1..]
+            .iter()
+            .filter_map(|line| {
+                let value: Vec<&str> = line.split_whitespace().collect();
+                if value.len() <= command {
+                    return None;
+                }
+                Some(Process {
+                    pid: value[pid].parse().ok()?,
+                    usr: user.map(|idx| value[idx].to_string()).unwrap_or_default(),
+                    cpu: value[cpu].trim_end_matches('%').parse().ok()?,
+                    mem: value[mem].trim_end_matches('%').parse().ok()?,
+                    time: time.map(|idx| value[idx].to_string()).unwrap_or_default(),
+                    // COMMAND位于最后一列，可能包含空格
+                    command: value[command..].join(" "),
+                })
+            })
+            .collect()
+    }
+}
+
+/// `top -H`输出中的单个线程，pid为线程的十进制ID
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CpuThread {
+    pub file_id: String,
+    pub work_space: String,
+    pub pid: u32,
+    /// 与jstack中nid格式一致的十六进制线程ID
+    pub nid: String,
+    pub user: String,
+    pub cpu: f64,
+    pub mem: f64,
+    pub cpu_time: String,
+    pub command: String,
+}
+
+impl CpuThread {
+    pub fn parse(lines: &[String], file_id: &str, work_space: &str) -> Vec<CpuThread> {
+        Process::parse_table(lines)
+            .into_iter()
+            .map(|row| CpuThread {
+                file_id: file_id.into(),
+                work_space: work_space.into(),
+                pid: row.pid,
+                nid: Self::to_nid(row.pid),
+                user: row.usr,
+                cpu: row.cpu,
+                mem: row.mem,
+                cpu_time: row.time,
+                command: row.command,
+            })
+            .collect()
+    }
+
+    pub fn to_nid(pid: u32) -> String {
+        format!("0x{:x}", pid)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct HotThreadQuery {
+    pub limit: Option<usize>,
+}
+
+/// CPU占用较高的线程及其在最近一次jstack中的堆栈
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct HotThread {
+    pub file_id: String,
+    pub time: Option<NaiveDateTime>,
+    pub pid: u32,
+    pub nid: String,
+    pub cpu: f64,
+    pub mem: f64,
+    pub cpu_time: String,
+    pub command: String,
+    pub dump_file_id: Option<String>,
+    pub thread_id: Option<String>,
+    pub thread_name: Option<String>,
+    pub status: Option<ThreadStatus>,
+    pub stack: Vec<String>,
+}
+
+impl HotThread {
+    /// 选出采集时间与`time`最接近的dump文件，时间未知时只有一个dump文件才能确定
+    pub fn closest_dump(time: Option<NaiveDateTime>, dumps: &[(String, Option<NaiveDateTime>)]) -> Option<String> {
+        match time {
+            Some(time) => dumps
+                .iter()
+                .filter_map(|(id, dump_time)| dump_time.map(|t| (id, (t - time).num_seconds().abs())))
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(id, _)| id.clone()),
+            None if dumps.len() == 1 => Some(dumps[0].0.clone()),
+            None => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ProcessQuery {
+    pub limit: Option<usize>,
+}
+
+/// 单个进程在各个采集时间点的CPU和内存占用，未出现的时间点为None
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProcessSeries {
+    pub pid: u32,
+    pub user: String,
+    pub command: String,
+    pub cpu: Vec<Option<f64>>,
+    pub mem: Vec<Option<f64>>,
+}
+
+/// 按进程统计的CPU占用，与CpuCount一样以exe_time为横轴
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProcessCpuCount {
+    pub exe_time: Vec<NaiveDateTime>,
+    pub processes: Vec<ProcessSeries>,
+}
+
+impl ProcessCpuCount {
+    /// 根据(采集时间, 进程)样本生成图表数据，只保留CPU峰值最高的`limit`个进程
+    pub fn new(samples: Vec<(NaiveDateTime, Process)>, limit: usize) -> Self {
+        let mut exe_time: Vec<NaiveDateTime> = samples.iter().map(|(time, _)| *time).collect();
+        exe_time.sort();
+        exe_time.dedup();
+        let mut processes: Vec<ProcessSeries> = Vec::new();
+        let mut index: HashMap<(u32, String), usize> = HashMap::new();
+        for (time, process) in samples {
+            let idx = *index.entry((process.pid, process.command.clone())).or_insert_with(|| {
+                processes.push(ProcessSeries {
+                    pid: process.pid,
+                    user: process.usr.clone(),
+                    command: process.command.clone(),
+                    cpu: vec![None; exe_time.len()],
+                    mem: vec![None; exe_time.len()],
+                });
+                processes.len() - 1
+            });
+            if let Ok(pos) = exe_time.binary_search(&time) {
+                processes[idx].cpu[pos] = Some(process.cpu);
+                processes[idx].mem[pos] = Some(process.mem);
+            }
+        }
+        let peak = |series: &ProcessSeries| series.cpu.iter().flatten().copied().fold(0.0, f64::max);
+        processes.sort_by(|a, b| peak(b).total_cmp(&peak(a)).then_with(|| a.pid.cmp(&b.pid)));
+        processes.truncate(limit);
+        ProcessCpuCount { exe_time, processes }
+    }
+}
+
 #[derive(Serialize)]
 pub struct CpuCount {
-    pub exe_time: Vec<NaiveTime>,
+    pub exe_time: Vec<NaiveDateTime>,
+    pub load1: Vec<f64>,
+    pub load5: Vec<f64>,
+    pub load15: Vec<f64>,
     pub us: Vec<f64>,
     pub sy: Vec<f64>,
+    pub ni: Vec<f64>,
     pub ids: Vec<f64>,
+    pub wa: Vec<f64>,
+    pub hi: Vec<f64>,
+    pub si: Vec<f64>,
+    pub st: Vec<f64>,
+    pub buff_cache: Vec<f64>,
+    pub swap_total: Vec<f64>,
+    pub swap_free: Vec<f64>,
+    pub swap_used: Vec<f64>,
+    /// 每个采集点缓存和交换区字段的单位，历史数据单位未知时为None
+    pub mem_unit: Vec<Option<String>>,
 }
 
 #[cfg(test)]
 pub mod test {
-    use super::Cpu;
+    use super::{Cpu, CpuThread, HotThread, Process, ProcessCpuCount};
+    use common::error::LineError;
     use std::{
         fs,
         io::{self, BufRead},
@@ -178,7 +462,134 @@ pub mod test {
             lines_storage.push(line.unwrap());
         }
 
-        let result = Cpu::new(lines_storage,"A","c");
+        let result = Cpu::new(lines_storage,"A","c", None);
         println!("{:?}", result);
     }
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> Option<chrono::NaiveDateTime> {
+        chrono::NaiveDate::from_ymd_opt(year, month, day).and_then(|d| d.and_hms_opt(hour, minute, second))
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_procps_header() {
+        let cpu = Cpu::new(lines("top - 17:01:36 up 120 days,  3:11,  2 users,  load average: 1.25, 0.98, 0.87
+Tasks: 312 total,   2 running, 310 sleeping,   0 stopped,   0 zombie
+%Cpu(s): 12.5 us,  3.1 sy,  0.2 ni, 83.9 id,  0.3 wa,  0.1 hi,  0.2 si,  0.4 st
+MiB Mem :  32011.6 total,   1205.6 free,  19531.2 used,  11274.8 buff/cache
+MiB Swap:   4096.0 total,   4000.0 free,     96.0 used.  11718.8 avail Mem
+
+  PID USER      PR  NI    VIRT    RES    SHR S  %CPU %MEM     TIME+ COMMAND
+17606 app       20   0   12.3g   4.1g  20000 S  85.0 13.1 120:33.12 java"), "f", "w", time(2024, 8, 9, 17, 1, 0)).unwrap();
+        assert_eq!(cpu.exe_time, time(2024, 8, 9, 17, 1, 36).unwrap());
+        assert_eq!((cpu.load1, cpu.load5, cpu.load15), (1.25, 0.98, 0.87));
+        assert_eq!((cpu.tasks, cpu.running, cpu.sleeping), (312, 2, 310));
+        assert_eq!((cpu.us, cpu.sy, cpu.ni, cpu.ids), (12.5, 3.1, 0.2, 83.9));
+        assert_eq!((cpu.wa, cpu.hi, cpu.si, cpu.st), (0.3, 0.1, 0.2, 0.4));
+        assert_eq!(cpu.mem_total, 32011.6 * 1024.0);
+        assert_eq!(cpu.buff_cache, 11274.8 * 1024.0);
+        assert_eq!((cpu.swap_total, cpu.swap_free, cpu.swap_used), (4096.0 * 1024.0, 4000.0 * 1024.0, 96.0 * 1024.0));
+        assert_eq!(cpu.processes.len(), 1);
+    }
+
+    #[test]
+    pub fn test_legacy_procps_header() {
+        let cpu = Cpu::new(lines("top - 09:15:02 up 3 days,  1:02,  1 user,  load average: 0.52, 0.58, 0.59
+Tasks: 120 total,   1 running, 119 sleeping,   0 stopped,   0 zombie
+Cpu(s):  5.0%us,  1.0%sy,  0.0%ni, 90.0%id,  4.0%wa,  0.0%hi,  0.0%si,  0.0%st
+Mem:   8062456k total,  7900000k used,   162456k free,   300000k buffers
+Swap:  2097148k total,        0k used,  2097148k free,  5000000k cached"), "f", "w", time(2024, 8, 9, 23, 59, 0)).unwrap();
+        // 采集目录在零点前创建，top输出的时间已经是第二天
+        assert_eq!(cpu.exe_time, time(2024, 8, 10, 9, 15, 2).unwrap());
+        assert_eq!((cpu.us, cpu.wa, cpu.ids), (5.0, 4.0, 90.0));
+        assert_eq!((cpu.mem_total, cpu.mem_used, cpu.mem_free), (8062456.0, 7900000.0, 162456.0));
+        assert_eq!(cpu.buff_cache, 5300000.0);
+        assert_eq!(cpu.swap_total, 2097148.0);
+    }
+
+    #[test]
+    pub fn test_busybox_header() {
+        let cpu = Cpu::new(lines("Mem: 1922420K used, 69996K free, 0K shrd, 118828K buff, 1219148K cached
+CPU:   2% usr   1% sys   0% nic  93% idle   4% io   0% irq   0% sirq
+Load average: 0.08 0.05 0.01 2/180 12345
+  PID  PPID USER     STAT   VSZ %VSZ CPU %CPU COMMAND
+ 3021     1 app      S     2.1g 110%   1  12% /usr/bin/java -jar app.jar"), "f", "w", time(2024, 8, 9, 17, 1, 36)).unwrap();
+        assert_eq!(cpu.exe_time, time(2024, 8, 9, 17, 1, 36).unwrap());
+        assert_eq!((cpu.load1, cpu.load5, cpu.load15), (0.08, 0.05, 0.01));
+        assert_eq!((cpu.tasks, cpu.running, cpu.sleeping), (180, 2, 178));
+        assert_eq!((cpu.us, cpu.sy, cpu.ids, cpu.wa), (2.0, 1.0, 93.0, 4.0));
+        assert_eq!(cpu.mem_total, 1922420.0 + 69996.0);
+        assert_eq!(cpu.buff_cache, 118828.0 + 1219148.0);
+        assert_eq!(cpu.processes[0].command, "/usr/bin/java -jar app.jar");
+    }
+
+    #[test]
+    pub fn test_malformed_header() {
+        let time = time(2024, 8, 9, 17, 1, 36);
+        let err = Cpu::new(lines("top - 17:01:36 up 120 days,  load average: n/a
+%Cpu(s): 12.5 us,  3.1 sy, 83.9 id"), "f", "w", time).unwrap_err();
+        assert_eq!(err, LineError::new(Some(1), "无法解析系统负载"));
+        let err = Cpu::new(lines("top - 17:01:36 up 120 days,  load average: 1.25, 0.98, 0.87
+%Cpu(s): n/a"), "f", "w", time).unwrap_err();
+        assert_eq!(err.line, Some(2));
+        let err = Cpu::new(lines("Mem: 1922420K used, 69996K free"), "f", "w", time).unwrap_err();
+        assert_eq!(err, LineError::new(None, "未找到CPU使用率行"));
+        let err = Cpu::new(lines("CPU:   2% usr   1% sys  93% idle"), "f", "w", None).unwrap_err();
+        assert_eq!(err, LineError::new(None, "无法确定采集时间"));
+        let err = Cpu::new(lines("top - 17:01:36 up 120 days,  load average: 1.25, 0.98, 0.87"), "f", "w", None).unwrap_err();
+        assert_eq!(err, LineError::new(Some(1), "无法确定采集日期"));
+        assert!(Cpu::new(vec![], "f", "w", time).is_err());
+    }
+
+    #[test]
+    pub fn test_cpu_thread() {
+        let lines: Vec<String> = vec![
+            "top - 17:01:36 up 120 days,  3:11,  2 users,  load average: 1.25, 0.98, 0.87",
+            "Threads:  45 total,   1 running,  44 sleeping,   0 stopped,   0 zombie",
+            "",
+            "  PID USER      PR  NI    VIRT    RES    SHR S  %CPU %MEM     TIME+ COMMAND",
+            "17620 app       20   0   12.3g   4.1g  20000 R  93.8 13.1   5:12.33 java",
+            "17621 app       20   0   12.3g   4.1g  20000 S   1.2 13.1   0:01.02 VM Thread",
+        ].into_iter().map(|l| l.to_string()).collect();
+        let threads = CpuThread::parse(&lines, "f", "w");
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].nid, "0x44d4");
+        assert_eq!(threads[0].cpu, 93.8);
+        assert_eq!(threads[0].cpu_time, "5:12.33");
+        assert_eq!(threads[1].command, "VM Thread");
+    }
+
+    #[test]
+    pub fn test_closest_dump() {
+        let time = |minute| chrono::NaiveDate::from_ymd_opt(2024, 8, 9).and_then(|d| d.and_hms_opt(17, minute, 0));
+        let dumps = vec![("a".to_string(), time(1)), ("b".to_string(), time(5)), ("c".to_string(), None)];
+        assert_eq!(HotThread::closest_dump(time(2), &dumps), Some("a".to_string()));
+        assert_eq!(HotThread::closest_dump(time(4), &dumps), Some("b".to_string()));
+        assert_eq!(HotThread::closest_dump(None, &dumps), None);
+        assert_eq!(HotThread::closest_dump(None, &dumps[..1]), Some("a".to_string()));
+    }
+
+    #[test]
+    pub fn test_process_cpu_count() {
+        let lines = |java_cpu: &str| -> Vec<String> {
+            vec![
+                "top - 17:01:36 up 120 days,  3:11,  2 users,  load average: 1.25, 0.98, 0.87".to_string(),
+                "  PID USER      PR  NI    VIRT    RES    SHR S  %CPU %MEM     TIME+ COMMAND".to_string(),
+                format!("17606 app       20   0   12.3g   4.1g  20000 S  {} 13.1 120:33.12 java", java_cpu),
+                " 1234 root      20   0  162000   2300   1600 R   6.2  0.0   0:00.02 top".to_string(),
+            ]
+        };
+        let time = |minute| time(2024, 8, 9, 17, minute, 36).unwrap();
+        let mut samples: Vec<_> = Process::parse_table(&lines("85.0")).into_iter().map(|p| (time(2), p)).collect();
+        samples.extend(Process::parse_table(&lines("20.5")).into_iter().take(1).map(|p| (time(1), p)));
+        let count = ProcessCpuCount::new(samples, 10);
+        assert_eq!(count.exe_time, vec![time(1), time(2)]);
+        assert_eq!(count.processes[0].command, "java");
+        assert_eq!(count.processes[0].cpu, vec![Some(20.5), Some(85.0)]);
+        assert_eq!(count.processes[1].cpu, vec![None, Some(6.2)]);
+        assert_eq!(ProcessCpuCount::new(vec![], 10).processes.len(), 0);
+    }
 }
@@ -0,0 +1,55 @@
+use serde::Serialize;
+
+use crate::model::thread::ThreadStatus;
+
+/// 只在其中一个dump中出现的线程
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThreadPresence {
+    pub thread_id: String,
+    pub name: String,
+    pub nid: String,
+    pub status: ThreadStatus,
+    pub top_method: String,
+}
+
+/// 两个dump中状态不同的同一个线程
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThreadStateChange {
+    pub name: String,
+    pub nid: String,
+    pub base_thread_id: String,
+    pub target_thread_id: String,
+    pub base_status: ThreadStatus,
+    pub target_status: ThreadStatus,
+    pub base_top_method: String,
+    pub target_top_method: String,
+}
+
+/// 线程池（按线程名前缀归类）的线程数变化
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PoolDelta {
+    pub name: String,
+    pub base_count: usize,
+    pub target_count: usize,
+    pub delta: i64,
+}
+
+/// 方法出现在线程堆栈中的次数变化，每个线程最多计一次
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MethodDelta {
+    pub method: String,
+    pub base_samples: usize,
+    pub target_samples: usize,
+    pub delta: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DumpDiff {
+    pub base_file_id: String,
+    pub target_file_id: String,
+    pub appeared: Vec<ThreadPresence>,
+    pub disappeared: Vec<ThreadPresence>,
+    pub changed: Vec<ThreadStateChange>,
+    pub pools: Vec<PoolDelta>,
+    pub methods: Vec<MethodDelta>,
+}
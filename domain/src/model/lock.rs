@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use common::error::ThreadError;
+
+use crate::model::thread::{Frame, MonitorAction, Thread, ThreadStatus};
+
+/// 线程正在等待进入的监视器
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitPoint {
+    pub address: u64,
+    pub class_name: String,
+    /// 发生等待的方法帧
+    pub frame: Option<String>,
+}
+
+/// 死锁环中的单个线程
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeadlockThread {
+    pub thread_name: String,
+    pub nid: String,
+    pub status: ThreadStatus,
+    pub lock_address: String,
+    pub lock_class: String,
+    pub waiting_frame: Option<String>,
+    pub owner_name: String,
+}
+
+/// 一个死锁环，按等待方向排列：每个线程等待的锁都被下一个线程持有
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeadlockCycle {
+    pub threads: Vec<DeadlockThread>,
+}
+
+/// 锁相关栈帧的动作，持久化到LOCK_INFO表中
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[repr(i8)]
+pub enum LockAction {
+    Locked = 1,
+    WaitingToLock = 2,
+    WaitingOn = 3,
+    Parking = 4,
+    Unknown = 0,
+}
+
+impl TryFrom<i8> for LockAction {
+    type Error = ThreadError;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(LockAction::Locked),
+            2 => Ok(LockAction::WaitingToLock),
+            3 => Ok(LockAction::WaitingOn),
+            4 => Ok(LockAction::Parking),
+            _ => Ok(LockAction::Unknown),
+        }
+    }
+}
+
+impl From<LockAction> for i8 {
+    fn from(action: LockAction) -> Self {
+        action as i8
+    }
+}
+
+impl LockAction {
+    /// 提取栈帧中的锁地址和动作，非锁相关的栈帧返回None
+    pub fn from_frame(frame: &Frame) -> Option<(u64, LockAction)> {
+        match frame {
+            Frame::Lock { lock_address } => Some((*lock_address, LockAction::Locked)),
+            Frame::Monitor {
+                monitor_address,
+                action,
+            } => Some((
+                *monitor_address,
+                match action {
+                    MonitorAction::WaitingToLock => LockAction::WaitingToLock,
+                    MonitorAction::WaitingOn => LockAction::WaitingOn,
+                    MonitorAction::Locked => LockAction::Locked,
+                },
+            )),
+            Frame::Parking { parking_address } => Some((*parking_address, LockAction::Parking)),
+            _ => None,
+        }
+    }
+}
+
+/// 线程在锁上等待的方式
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum ContentionKind {
+    /// synchronized监视器，`- waiting to lock`
+    Monitor,
+    /// java.util.concurrent锁，`- parking to wait for`
+    Parking,
+}
+
+/// 等待者栈顶方法的出现次数
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct MethodCount {
+    pub method: String,
+    pub count: usize,
+}
+
+/// 单个锁地址上的竞争情况
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct LockContention {
+    pub lock_address: String,
+    pub lock_class: String,
+    pub kind: ContentionKind,
+    pub owner_name: Option<String>,
+    pub owner_nid: Option<String>,
+    pub waiters: usize,
+    pub waiter_names: Vec<String>,
+    pub top_methods: Vec<MethodCount>,
+}
+
+/// 格式化为jstack中的锁地址形式
+pub fn format_address(address: u64) -> String {
+    format!("0x{:016x}", address)
+}
+
+/// 线程持有的监视器地址，排除通过Object.wait()已经释放的监视器
+pub fn held_monitors(thread: &Thread) -> Vec<u64> {
+    let released: HashSet<u64> = thread
+        .frames
+        .iter()
+        .filter_map(|frame| match frame.frame {
+            Frame::Monitor {
+                monitor_address,
+                action: MonitorAction::WaitingOn,
+            } => Some(monitor_address),
+            _ => None,
+        })
+        .collect();
+    thread
+        .frames
+        .iter()
+        .filter_map(|frame| match frame.frame {
+            Frame::Lock { lock_address } if !released.contains(&lock_address) => Some(lock_address),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 线程正在等待进入的监视器（`- waiting to lock`）
+pub fn waiting_monitor(thread: &Thread) -> Option<WaitPoint> {
+    thread
+        .frames
+        .iter()
+        .enumerate()
+        .find_map(|(idx, call)| match call.frame {
+            Frame::Monitor {
+                monitor_address,
+                action: MonitorAction::WaitingToLock,
+            } => Some(WaitPoint {
+                address: monitor_address,
+                class_name: call.class_name.clone(),
+                frame: thread.frames[..idx]
+                    .iter()
+                    .rev()
+                    .find(|f| matches!(f.frame, Frame::MethodCall | Frame::NativeMethod))
+                    .and_then(|f| f.signature.clone()),
+            }),
+            _ => None,
+        })
+}
+
+/// 线程正在竞争的锁，包括监视器和park等待的同步器
+pub fn contended_lock(thread: &Thread) -> Option<(u64, String, ContentionKind)> {
+    thread.frames.iter().find_map(|call| match call.frame {
+        Frame::Monitor {
+            monitor_address,
+            action: MonitorAction::WaitingToLock,
+        } => Some((monitor_address, call.class_name.clone(), ContentionKind::Monitor)),
+        Frame::Parking { parking_address } => {
+            Some((parking_address, call.class_name.clone(), ContentionKind::Parking))
+        }
+        _ => None,
+    })
+}
+
+/// 按锁地址对等待中的线程分组，按等待线程数从多到少排列
+pub fn count_contention(threads: &[Thread]) -> Vec<LockContention> {
+    let mut owners: HashMap<u64, &Thread> = HashMap::new();
+    for thread in threads {
+        for address in held_monitors(thread) {
+            owners.entry(address).or_insert(thread);
+        }
+    }
+    let mut groups: HashMap<u64, LockContention> = HashMap::new();
+    let mut methods: HashMap<u64, HashMap<String, usize>> = HashMap::new();
+    for thread in threads {
+        let Some((address, class_name, kind)) = contended_lock(thread) else {
+            continue;
+        };
+        let owner = owners.get(&address);
+        let entry = groups.entry(address).or_insert_with(|| LockContention {
+            lock_address: format_address(address),
+            lock_class: class_name,
+            kind,
+            owner_name: owner.map(|t| t.name.clone()),
+            owner_nid: owner.map(|t| t.nid.clone()),
+            waiters: 0,
+            waiter_names: vec![],
+            top_methods: vec![],
+        });
+        entry.waiters += 1;
+        entry.waiter_names.push(thread.name.clone());
+        let top_method = thread
+            .frames
+            .first()
+            .and_then(|frame| frame.signature.clone())
+            .unwrap_or_default();
+        *methods.entry(address).or_default().entry(top_method).or_insert(0) += 1;
+    }
+    let mut result: Vec<LockContention> = groups
+        .into_iter()
+        .map(|(address, mut contention)| {
+            let mut top_methods: Vec<MethodCount> = methods
+                .remove(&address)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(method, count)| MethodCount { method, count })
+                .collect();
+            top_methods.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.method.cmp(&b.method)));
+            contention.top_methods = top_methods;
+            contention
+        })
+        .collect();
+    result.sort_by(|a, b| b.waiters.cmp(&a.waiters).then_with(|| a.lock_address.cmp(&b.lock_address)));
+    result
+}
+
+/// 根据同一个dump文件中的全部线程构建等待图，并找出其中的死锁环
+pub fn find_deadlocks(threads: &[Thread]) -> Vec<DeadlockCycle> {
+    let mut owners: HashMap<u64, usize> = HashMap::new();
+    for (idx, thread) in threads.iter().enumerate() {
+        for address in held_monitors(thread) {
+            owners.entry(address).or_insert(idx);
+        }
+    }
+    let waits: Vec<Option<WaitPoint>> = threads.iter().map(waiting_monitor).collect();
+    // 每个线程最多等待一把锁，因此等待图中每个节点最多只有一条出边
+    let next: Vec<Option<usize>> = waits
+        .iter()
+        .enumerate()
+        .map(|(idx, wait)| {
+            wait.as_ref()
+                .and_then(|w| owners.get(&w.address).copied())
+                .filter(|owner| *owner != idx)
+        })
+        .collect();
+
+    let mut cycles = Vec::new();
+    // 0: 未访问, 1: 当前路径上, 2: 已处理
+    let mut state = vec![0u8; threads.len()];
+    for start in 0..threads.len() {
+        let mut path = Vec::new();
+        let mut current = Some(start);
+        while let Some(idx) = current {
+            match state[idx] {
+                0 => {
+                    state[idx] = 1;
+                    path.push(idx);
+                    current = next[idx];
+                }
+                1 => {
+                    let begin = path.iter().position(|p| *p == idx).unwrap_or(0);
+                    cycles.push(build_cycle(threads, &waits, &next, &path[begin..]));
+                    break;
+                }
+                _ => break,
+            }
+        }
+        for idx in path {
+            state[idx] = 2;
+        }
+    }
+    cycles
+}
+
+fn build_cycle(
+    threads: &[Thread],
+    waits: &[Option<WaitPoint>],
+    next: &[Option<usize>],
+    members: &[usize],
+) -> DeadlockCycle {
+    // 从dump中最先出现的线程开始输出，保证结果稳定
+    let offset = members
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, idx)| **idx)
+        .map(|(pos, _)| pos)
+        .unwrap_or(0);
+    let cycle = members
+        .iter()
+        .cycle()
+        .skip(offset)
+        .take(members.len())
+        .filter_map(|idx| {
+            let wait = waits[*idx].as_ref()?;
+            let owner = next[*idx]?;
+            let thread = &threads[*idx];
+            Some(DeadlockThread {
+                thread_name: thread.name.clone(),
+                nid: thread.nid.clone(),
+                status: thread.status.clone(),
+                lock_address: format_address(wait.address),
+                lock_class: wait.class_name.clone(),
+                waiting_frame: wait.frame.clone(),
+                owner_name: threads[owner].name.clone(),
+            })
+        })
+        .collect();
+    DeadlockCycle { threads: cycle }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn thread(lines: &[&str]) -> Thread {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        Thread::new(&lines, 0, 0).unwrap()
+    }
+
+    fn deadlocked_pair() -> Vec<Thread> {
+        vec![
+            thread(&[
+                "\"worker-1\" #11 prio=5 os_prio=0 tid=0x00007f3d70001800 nid=0x2f01 waiting for monitor entry [0x00007f3d80f21000]",
+                "java.lang.Thread.State: BLOCKED (on object monitor)",
+                "at com.example.Transfer.debit(Transfer.java:20)",
+                "- waiting to lock <0x00000000c7c600d0> (a com.example.Account)",
+                "at com.example.Transfer.run(Transfer.java:12)",
+                "- locked <0x00000000c7c600e0> (a com.example.Ledger)",
+                "at java.lang.Thread.run(Thread.java:748)",
+            ]),
+            thread(&[
+                "\"worker-2\" #12 prio=5 os_prio=0 tid=0x00007f3d70002800 nid=0x2f02 waiting for monitor entry [0x00007f3d80f22000]",
+                "java.lang.Thread.State: BLOCKED (on object monitor)",
+                "at com.example.Transfer.credit(Transfer.java:30)",
+                "- waiting to lock <0x00000000c7c600e0> (a com.example.Ledger)",
+                "at com.example.Transfer.run(Transfer.java:14)",
+                "- locked <0x00000000c7c600d0> (a com.example.Account)",
+                "at java.lang.Thread.run(Thread.java:748)",
+            ]),
+        ]
+    }
+
+    #[test]
+    pub fn test_find_deadlock() {
+        let cycles = find_deadlocks(&deadlocked_pair());
+        assert_eq!(cycles.len(), 1);
+        let cycle = &cycles[0].threads;
+        assert_eq!(cycle.len(), 2);
+        assert_eq!(cycle[0].thread_name, "worker-1");
+        assert_eq!(cycle[0].lock_address, "0x00000000c7c600d0");
+        assert_eq!(cycle[0].lock_class, "com.example.Account");
+        assert_eq!(cycle[0].waiting_frame.as_deref(), Some("com.example.Transfer.debit(Transfer.java:20)"));
+        assert_eq!(cycle[0].owner_name, "worker-2");
+        assert_eq!(cycle[1].thread_name, "worker-2");
+        assert_eq!(cycle[1].lock_class, "com.example.Ledger");
+        assert_eq!(cycle[1].owner_name, "worker-1");
+    }
+
+    #[test]
+    pub fn test_blocked_without_cycle() {
+        let mut threads = deadlocked_pair();
+        threads.truncate(1);
+        threads.push(thread(&[
+            "\"worker-3\" #13 prio=5 os_prio=0 tid=0x00007f3d70003800 nid=0x2f03 runnable [0x00007f3d80f23000]",
+            "java.lang.Thread.State: RUNNABLE",
+            "at com.example.Account.audit(Account.java:40)",
+            "- locked <0x00000000c7c600d0> (a com.example.Account)",
+            "at java.lang.Thread.run(Thread.java:748)",
+        ]));
+        assert!(find_deadlocks(&threads).is_empty());
+    }
+
+    #[test]
+    pub fn test_count_contention() {
+        let mut threads = deadlocked_pair();
+        threads.truncate(1);
+        threads.push(thread(&[
+            "\"worker-3\" #13 prio=5 os_prio=0 tid=0x00007f3d70003800 nid=0x2f03 runnable [0x00007f3d80f23000]",
+            "java.lang.Thread.State: RUNNABLE",
+            "at com.example.Account.audit(Account.java:40)",
+            "- locked <0x00000000c7c600d0> (a com.example.Account)",
+            "at java.lang.Thread.run(Thread.java:748)",
+        ]));
+        threads.push(thread(&[
+            "\"worker-4\" #14 prio=5 os_prio=0 tid=0x00007f3d70004800 nid=0x2f04 waiting for monitor entry [0x00007f3d80f24000]",
+            "java.lang.Thread.State: BLOCKED (on object monitor)",
+            "at com.example.Transfer.debit(Transfer.java:20)",
+            "- waiting to lock <0x00000000c7c600d0> (a com.example.Account)",
+            "at java.lang.Thread.run(Thread.java:748)",
+        ]));
+        threads.push(thread(&[
+            "\"pool-1\" #15 prio=5 os_prio=0 tid=0x00007f3d70005800 nid=0x2f05 waiting on condition [0x00007f3d80f25000]",
+            "java.lang.Thread.State: WAITING (parking)",
+            "at sun.misc.Unsafe.park(Native Method)",
+            "- parking to wait for <0x00000000c7c60100> (a java.util.concurrent.locks.ReentrantLock$NonfairSync)",
+            "at java.util.concurrent.locks.LockSupport.park(LockSupport.java:175)",
+        ]));
+        let result = count_contention(&threads);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].lock_address, "0x00000000c7c600d0");
+        assert_eq!(result[0].kind, ContentionKind::Monitor);
+        assert_eq!(result[0].owner_name.as_deref(), Some("worker-3"));
+        assert_eq!(result[0].waiters, 2);
+        assert_eq!(result[0].top_methods, vec![MethodCount {
+            method: "com.example.Transfer.debit(Transfer.java:20)".to_string(),
+            count: 2,
+        }]);
+        assert_eq!(result[1].kind, ContentionKind::Parking);
+        assert_eq!(result[1].lock_class, "java.util.concurrent.locks.ReentrantLock$NonfairSync");
+        assert_eq!(result[1].owner_name, None);
+        assert_eq!(result[1].waiters, 1);
+    }
+
+    #[test]
+    pub fn test_lock_action_from_frame() {
+        let threads = deadlocked_pair();
+        let actions: Vec<(u64, LockAction)> = threads[0]
+            .frames
+            .iter()
+            .filter_map(|call| LockAction::from_frame(&call.frame))
+            .collect();
+        assert_eq!(actions, vec![
+            (0x00000000c7c600d0, LockAction::WaitingToLock),
+            (0x00000000c7c600e0, LockAction::Locked),
+        ]);
+        assert_eq!(LockAction::try_from(i8::from(LockAction::Parking)).unwrap(), LockAction::Parking);
+    }
+
+    #[test]
+    pub fn test_waiting_on_releases_monitor() {
+        let waiting = thread(&[
+            "\"consumer\" #14 prio=5 os_prio=0 tid=0x00007f3d70004800 nid=0x2f04 in Object.wait() [0x00007f3d80f24000]",
+            "java.lang.Thread.State: WAITING (on object monitor)",
+            "at java.lang.Object.wait(Native Method)",
+            "- waiting on <0x00000000c7c600f0> (a java.util.LinkedList)",
+            "at java.lang.Object.wait(Object.java:502)",
+            "at com.example.Queue.take(Queue.java:18)",
+            "- locked <0x00000000c7c600f0> (a java.util.LinkedList)",
+        ]);
+        assert!(held_monitors(&waiting).is_empty());
+        assert_eq!(waiting_monitor(&waiting), None);
+    }
+}
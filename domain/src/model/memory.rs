@@ -1,151 +1,496 @@
 use chrono::{Duration, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use core::f64;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{BufRead};
-use std::{fs, io, path::Path};
+use std::{io, path::Path};
 
-#[derive(Serialize,Deserialize,Debug, PartialEq)]
+use crate::db::db_memory::{DBMemory, DBMemorySource};
+use crate::db::db_memory_percent::{DBMemoryPercent, DBMemoryPercentSource};
+
+/// jstat -gc/-gccapacity的一次采样，容量和使用量的单位为KB，YGC/FGC/CGC为累计GC次数。
+/// 列按表头名称映射，同一时间点的-gc和-gccapacity输出合并为一条记录，没有输出的列为0
+#[derive(Serialize,Deserialize,Debug, PartialEq, Default)]
 pub struct MemoryValue {
     pub file_id: String,
     pub time: Option<NaiveDateTime>,
-    pub value: Vec<f64>,
+    pub s0c: f64,
+    pub s1c: f64,
+    pub s0u: f64,
+    pub s1u: f64,
+    pub ec: f64,
+    pub eu: f64,
+    pub oc: f64,
+    pub ou: f64,
+    pub mc: f64,
+    pub mu: f64,
+    pub ccsc: f64,
+    pub ccsu: f64,
+    pub ygc: f64,
+    pub ygct: f64,
+    pub fgc: f64,
+    pub fgct: f64,
+    pub cgc: f64,
+    pub cgct: f64,
+    pub gct: f64,
+    pub ngcmn: f64,
+    pub ngcmx: f64,
+    pub ngc: f64,
+    pub ogcmn: f64,
+    pub ogcmx: f64,
+    pub ogc: f64,
+    pub mcmn: f64,
+    pub mcmx: f64,
+    pub ccsmn: f64,
+    pub ccsmx: f64,
+    /// 无法识别的列，如-gcnew的TT/MTT/DSS
+    pub extras: BTreeMap<String, f64>,
 }
-#[derive(Serialize,Deserialize, Debug, PartialEq)]
+/// jstat -gcutil的一次采样，S0~CCS为各区域的使用率（%），YGC/FGC/CGC为累计GC次数
+#[derive(Serialize,Deserialize, Debug, PartialEq, Default)]
 pub struct MemoryPercent {
-    time: Option<NaiveDateTime>,
-    value: Vec<f64>,
+    pub file_id: String,
+    pub time: Option<NaiveDateTime>,
+    pub s0: f64,
+    pub s1: f64,
+    pub e: f64,
+    pub o: f64,
+    pub m: f64,
+    pub ccs: f64,
+    pub ygc: f64,
+    pub ygct: f64,
+    pub fgc: f64,
+    pub fgct: f64,
+    pub cgc: f64,
+    pub cgct: f64,
+    pub gct: f64,
+}
+
+/// 同一个JVM、同一种采集方式的以采集时间为横轴的堆内存使用率序列
+#[derive(Serialize, Debug, PartialEq)]
+pub struct MemoryPercentCount {
+    /// 文件名中的进程号，文件名中没有数字时为文件名
+    pub jvm: String,
+    /// gc目录下定时采集的文件为true，dump目录中随dump采集的快照为false
+    pub periodic: bool,
+    pub exe_time: Vec<Option<NaiveDateTime>>,
+    pub s0: Vec<f64>,
+    pub s1: Vec<f64>,
+    pub e: Vec<f64>,
+    pub o: Vec<f64>,
+    pub m: Vec<f64>,
+    pub ccs: Vec<f64>,
+    pub ygc: Vec<f64>,
+    pub fgc: Vec<f64>,
 }
 
 impl MemoryValue {
-    pub fn new(time: Option<NaiveDateTime>, filed_id: &str, line: &str) -> Self {
-        MemoryValue {
-            file_id: filed_id.into(),
+    pub fn new(time: Option<NaiveDateTime>, file_id: &str, header: &str, line: &str) -> Self {
+        let mut memory = MemoryValue {
+            file_id: file_id.into(),
             time,
-            value: Self::split_value(line),
-        }
+            ..Default::default()
+        };
+        memory.merge(header, line);
+        memory
     }
 
-    fn split_value(line: &str) -> Vec<f64> {
-        line.split_whitespace()
-            .map(|l| {
-                if l.eq("-") {
-                    0.0;
+    /// 按表头中的列名将一行-gc或-gccapacity输出合并到当前采样中，缺失或为"-"的列保持不变
+    pub fn merge(&mut self, header: &str, line: &str) {
+        for (name, value) in column_values(header, line) {
+            match name {
+                "S0C" => self.s0c = value,
+                "S1C" => self.s1c = value,
+                "S0U" => self.s0u = value,
+                "S1U" => self.s1u = value,
+                "EC" => self.ec = value,
+                "EU" => self.eu = value,
+                "OC" => self.oc = value,
+                "OU" => self.ou = value,
+                "MC" => self.mc = value,
+                "MU" => self.mu = value,
+                "CCSC" => self.ccsc = value,
+                "CCSU" => self.ccsu = value,
+                "YGC" => self.ygc = value,
+                "YGCT" => self.ygct = value,
+                "FGC" => self.fgc = value,
+                "FGCT" => self.fgct = value,
+                "CGC" => self.cgc = value,
+                "CGCT" => self.cgct = value,
+                "GCT" => self.gct = value,
+                "NGCMN" => self.ngcmn = value,
+                "NGCMX" => self.ngcmx = value,
+                "NGC" => self.ngc = value,
+                "OGCMN" => self.ogcmn = value,
+                "OGCMX" => self.ogcmx = value,
+                "OGC" => self.ogc = value,
+                "MCMN" => self.mcmn = value,
+                "MCMX" => self.mcmx = value,
+                "CCSMN" => self.ccsmn = value,
+                "CCSMX" => self.ccsmx = value,
+                // 采集时间已根据Timestamp推算
+                "Timestamp" => {}
+                _ => {
+                    self.extras.insert(name.to_string(), value);
                 }
-                match l.parse::<f64>() {
-                    Ok(value) => value,
-                    Err(_e) => 0.0,
-                }
-            })
-            .collect()
+            }
+        }
     }
 }
 
 impl MemoryPercent {
-    pub fn new(time: Option<NaiveDateTime>, line: &str) -> Self {
+    /// 按表头中的列名取值，不同JDK版本的列可能不同（如JDK 8没有CGC/CGCT），缺失或为"-"的列记为0
+    pub fn new(time: Option<NaiveDateTime>, file_id: &str, header: &str, line: &str) -> Self {
+        let values = column_values(header, line);
+        let value = |name: &str| values.get(name).copied().unwrap_or_default();
         MemoryPercent {
+            file_id: file_id.into(),
             time,
-            value: Self::split_value(line),
+            s0: value(S0),
+            s1: value(S1),
+            e: value(E),
+            o: value(O),
+            m: value(M),
+            ccs: value(CCS),
+            ygc: value(YGC),
+            ygct: value(YGCT),
+            fgc: value(FGC),
+            fgct: value(FGCT),
+            cgc: value(CGC),
+            cgct: value(CGCT),
+            gct: value(GCT),
         }
     }
+}
 
-    fn split_value(line: &str) -> Vec<f64> {
-        line.split_whitespace()
-            .map(|l| {
-                if l.eq("-") {
-                    0.00;
-                }
-                match l.parse::<f64>() {
-                    Ok(value) => value,
-                    Err(_e) => 0.00,
-                }
-            })
-            .collect()
+/// 将jstat的数据行按表头列名拆分，无法解析的值（如"-"）不会出现在结果中
+fn column_values<'a>(header: &'a str, line: &str) -> HashMap<&'a str, f64> {
+    header
+        .split_whitespace()
+        .zip(line.split_whitespace())
+        .filter_map(|(name, value)| value.parse::<f64>().ok().map(|value| (name, value)))
+        .collect()
+}
+
+/// jstat输出的表头类型，非数值开头的行为表头
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Header {
+    /// -gcutil
+    Percent,
+    /// -gc、-gccapacity等以KB为单位的输出
+    Value,
+    Other,
+}
+
+impl Header {
+    fn parse(line: &str) -> Option<Header> {
+        let first = line.split_whitespace().next()?;
+        if first == "-" || first.parse::<f64>().is_ok() {
+            return None;
+        }
+        let mut names = line.split_whitespace();
+        Some(if names.clone().any(|name| name == S0) {
+            Header::Percent
+        } else if names.any(|name| name == S0C || name == "NGCMN" || name == OC) {
+            Header::Value
+        } else {
+            Header::Other
+        })
     }
 }
 
-pub fn create(path: &str) -> (MemoryValue, MemoryPercent) {
-    let file_path = Path::new(path);
-    let path_time = file_path.parent().expect("无法获取上级");
-    let fmt = "%Y%m%d_%H%M%S";
-    let time = match NaiveDateTime::parse_from_str(path_time.file_name().unwrap().to_str().unwrap(), fmt){
-        Ok(time) => Some(time),
-        Err(_err) => None,
-    };
-    let file = fs::File::open(path).unwrap();
-    let reader = io::BufReader::new(file);
-    let mut idx = 0;
+/// 逐行读取jstat输出，对`kind`类型表头之后的每一行数据调用`sample`，兼容连续输出和每次采样都带表头两种格式。
+/// 采样时间优先根据-t输出的Timestamp列（JVM启动后的秒数）推算，否则从`start`开始按`cycle`秒递增，没有`cycle`时之后的采样时间为None
+fn read_samples(
+    reader: impl BufRead,
+    kind: Header,
+    start: Option<NaiveDateTime>,
+    cycle: Option<i64>,
+    mut sample: impl FnMut(Option<NaiveDateTime>, &str, &str),
+) -> io::Result<()> {
+    let mut header: Option<String> = None;
+    let mut first_timestamp: Option<f64> = None;
+    let mut current_time = start;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(parsed) = Header::parse(&line) {
+            header = (parsed == kind).then_some(line);
+            continue;
+        }
+        let Some(header) = header.as_deref() else {
+            continue;
+        };
+        let timestamp = column_values(header, &line).get(TIMESTAMP).copied();
+        let time = match (timestamp, start) {
+            (Some(timestamp), Some(start)) => {
+                let first = *first_timestamp.get_or_insert(timestamp);
+                start.checked_add_signed(Duration::milliseconds(((timestamp - first) * 1000.0) as i64))
+            }
+            _ => current_time,
+        };
+        sample(time, header, &line);
+        // 没有采集周期时只能确定第一行的采集时间
+        current_time = match (cycle, current_time) {
+            (Some(cycle), Some(time)) => time.checked_add_signed(Duration::seconds(cycle)),
+            _ => None,
+        };
+    }
+    Ok(())
+}
+
+/// 解析单次采集的jstat输出文件，采集时间取自所在目录名。
+/// 文件中依次包含-gcutil、-gc（可选-gccapacity）的输出，各取第一行数据，-gc和-gccapacity合并为一条记录
+pub fn create(path: &str, file_id: &str, reader: impl BufRead) -> io::Result<(Option<MemoryValue>, Option<MemoryPercent>)> {
+    let time = Path::new(path)
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .and_then(|name| name.to_str())
+        .and_then(|name| NaiveDateTime::parse_from_str(name, "%Y%m%d_%H%M%S").ok());
     let mut mem_info: Option<MemoryValue> = None;
     let mut mem_percent: Option<MemoryPercent> = None;
+    let mut header: Option<(Header, String)> = None;
     for line in reader.lines() {
-        match idx {
-            1 => mem_percent = Some(MemoryPercent::new(time, &line.unwrap())),
-            3 => mem_info = Some(MemoryValue::new(time,path, &line.unwrap())),
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(kind) = Header::parse(&line) {
+            header = Some((kind, line));
+            continue;
+        }
+        // 每个表头只取紧随其后的一行数据
+        match header.take() {
+            Some((Header::Percent, header)) if mem_percent.is_none() => {
+                mem_percent = Some(MemoryPercent::new(time, file_id, &header, &line));
+            }
+            Some((Header::Value, header)) => match mem_info.as_mut() {
+                Some(info) => info.merge(&header, &line),
+                None => mem_info = Some(MemoryValue::new(time, file_id, &header, &line)),
+            },
             _ => {}
         }
-        idx += 1;
     }
-    let mem_info = mem_info.expect("未找到MemoryInfo数据");
-    let mem_percent = mem_percent.expect("未找到MemoryPercent数据");
-    (mem_info, mem_percent)
+    Ok((mem_info, mem_percent))
 }
 
+/// 解析按周期追加的jstat -gc/-gccapacity输出，每一行数据为一次采样
 pub fn batch_crate_memory_info(
-    file_path: &str,
+    reader: impl BufRead,
+    file_id: &str,
     start: Option<NaiveDateTime>,
     cycle: Option<i64>,
-) -> Vec<MemoryValue> {
+) -> io::Result<Vec<MemoryValue>> {
     let mut memory_info = Vec::new();
-    let file = fs::File::open(file_path).unwrap();
-    let reader = io::BufReader::new(file);
-    let mut flag = false;
-    let mut current_time = start;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.contains(S0C) {
-            flag =true;
-            continue;
+    read_samples(reader, Header::Value, start, cycle, |time, header, line| {
+        memory_info.push(MemoryValue::new(time, file_id, header, line));
+    })?;
+    Ok(memory_info)
+}
+
+/// 解析jstat -gcutil的输出，每一行数据为一次采样
+pub fn batch_crate_memory_percent(
+    reader: impl BufRead,
+    file_id: &str,
+    start: Option<NaiveDateTime>,
+    cycle: Option<i64>,
+) -> io::Result<Vec<MemoryPercent>> {
+    let mut memory_percent = Vec::new();
+    read_samples(reader, Header::Percent, start, cycle, |time, header, line| {
+        memory_percent.push(MemoryPercent::new(time, file_id, header, line));
+    })?;
+    Ok(memory_percent)
+}
+
+/// 相邻两次jstat采样之间的GC活动，暂停时间单位为毫秒，内存单位为KB
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GcInterval {
+    pub start_time: NaiveDateTime,
+    pub end_time: NaiveDateTime,
+    pub young_gc: f64,
+    pub young_gc_per_minute: f64,
+    pub young_pause_avg: f64,
+    pub full_gc: f64,
+    pub full_gc_per_minute: f64,
+    pub full_pause_avg: f64,
+    /// GC耗时占采样间隔的百分比
+    pub gc_time_ratio: f64,
+    pub old_used: f64,
+    /// 老年代每分钟增长量，发生Full GC的区间中老年代会被回收，增长量为负
+    pub old_growth_per_minute: f64,
+}
+
+/// 工作空间整体的GC活动统计，用于判断性能下降是否由GC引起
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct GcActivity {
+    pub intervals: Vec<GcInterval>,
+    pub young_gc_per_minute: f64,
+    pub young_pause_avg: f64,
+    pub full_gc_per_minute: f64,
+    pub full_pause_avg: f64,
+    pub gc_time_ratio: f64,
+    /// 只统计没有发生Full GC的区间，近似为对象晋升到老年代的速率
+    pub old_growth_per_minute: f64,
+    pub old_used: f64,
+    /// 老年代可扩展到的最大容量，没有-gccapacity输出时为当前容量
+    pub old_capacity: f64,
+    /// 按老年代增长速率推算的耗尽时间（秒），老年代没有增长时为None
+    pub seconds_to_old_exhaustion: Option<f64>,
+    pub old_exhaustion_time: Option<NaiveDateTime>,
+}
+
+/// 同一个JVM、同一种采集方式的jstat采样的GC活动，不同序列的计数器不能相减
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct GcSeries {
+    /// 文件名中的进程号，文件名中没有数字时为文件名
+    pub jvm: String,
+    /// gc目录下定时采集的文件为true，dump目录中随dump采集的快照为false
+    pub periodic: bool,
+    pub activity: GcActivity,
+}
+
+impl GcSeries {
+    /// 按来源文件对采样分组，每个序列分别计算GC活动，采样需按采集时间升序排列
+    pub fn group(samples: Vec<DBMemorySource>) -> Vec<GcSeries> {
+        let mut groups: BTreeMap<(String, bool), Vec<DBMemory>> = BTreeMap::new();
+        for sample in samples {
+            groups.entry(series_key(&sample.source)).or_default().push(sample.memory);
         }
-        if flag {
-            let mem_info = MemoryValue::new(current_time, file_path, &line);
-            memory_info.push(mem_info);
-            if cycle.is_some() && current_time.is_some(){
-                current_time = current_time.unwrap().checked_add_signed(Duration::seconds(cycle.unwrap().into()));    
-            }
-            flag = false;
+        groups
+            .into_iter()
+            .map(|((jvm, periodic), samples)| GcSeries { jvm, periodic, activity: GcActivity::new(&samples) })
+            .collect()
+    }
+}
+
+impl MemoryPercentCount {
+    /// 按来源文件对使用率采样分组，不同JVM的使用率不能画在同一条曲线上，采样需按采集时间升序排列
+    pub fn group(samples: Vec<DBMemoryPercentSource>) -> Vec<MemoryPercentCount> {
+        let mut groups: BTreeMap<(String, bool), Vec<DBMemoryPercent>> = BTreeMap::new();
+        for sample in samples {
+            groups.entry(series_key(&sample.source)).or_default().push(sample.percent);
         }
+        groups
+            .into_iter()
+            .map(|((jvm, periodic), percents)| MemoryPercentCount {
+                jvm,
+                periodic,
+                exe_time: percents.iter().map(|p| p.exe_time).collect(),
+                s0: percents.iter().map(|p| p.s0).collect(),
+                s1: percents.iter().map(|p| p.s1).collect(),
+                e: percents.iter().map(|p| p.e).collect(),
+                o: percents.iter().map(|p| p.o).collect(),
+                m: percents.iter().map(|p| p.m).collect(),
+                ccs: percents.iter().map(|p| p.ccs).collect(),
+                ygc: percents.iter().map(|p| p.ygc).collect(),
+                fgc: percents.iter().map(|p| p.fgc).collect(),
+            })
+            .collect()
     }
-    memory_info
 }
 
-pub fn batch_crate_memory_percent(
-    file_path: &str,
-    start: Option<NaiveDateTime>,
-    cycle: Option<u32>,
-) -> Vec<MemoryPercent> {
-    let mut memory_info = Vec::new();
-    let file = fs::File::open(file_path).unwrap();
-    let reader = io::BufReader::new(file);
-    let mut flag = false;
-    let mut current_time = start;
-    for line in reader.lines() {
-        let line = line.unwrap();
-        if line.starts_with(S0) {
-            flag =true;
-            continue;
+/// jstat文件是否是gc目录下定时采集的文件，否则为dump目录中的快照
+pub fn is_periodic_gc(path: &str) -> bool {
+    Path::new(path)
+        .parent()
+        .is_some_and(|parent| parent.to_string_lossy().contains("gc"))
+}
+
+/// 采样所属的序列：文件名中最后一段数字作为进程号，以及是否定时采集
+pub(crate) fn series_key(path: &str) -> (String, bool) {
+    let path = path.replace('\\', "/");
+    let stem = Path::new(&path).file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+    let jvm = stem
+        .rsplit(|c: char| !c.is_ascii_digit())
+        .find(|digits| !digits.is_empty())
+        .unwrap_or(stem);
+    (jvm.to_string(), is_periodic_gc(&path))
+}
+
+impl GcActivity {
+    /// 根据同一序列中按采集时间升序排列的jstat采样计算GC频率、平均暂停时间和老年代增长，没有采集时间的采样会被忽略。
+    /// 计数器变小（JVM重启）的区间不参与统计
+    pub fn new(samples: &[DBMemory]) -> GcActivity {
+        let samples: Vec<(NaiveDateTime, &DBMemory)> = samples
+            .iter()
+            .filter_map(|sample| sample.exe_time.map(|time| (time, sample)))
+            .collect();
+        let mut activity = GcActivity::default();
+        let (mut seconds, mut young_gc, mut young_time, mut full_gc, mut full_time, mut gc_time) =
+            (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        let (mut growth, mut growth_seconds) = (0.0, 0.0);
+        for pair in samples.windows(2) {
+            let ((start_time, start), (end_time, end)) = (pair[0], pair[1]);
+            let elapsed = (end_time - start_time).num_milliseconds() as f64 / 1000.0;
+            let (ygc, ygct) = (end.ygc - start.ygc, end.ygct - start.ygct);
+            let (fgc, fgct) = (end.fgc - start.fgc, end.fgct - start.fgct);
+            let gct = end.gct - start.gct;
+            if elapsed <= 0.0 || ygc < 0.0 || fgc < 0.0 || gct < 0.0 {
+                continue;
+            }
+            let old_growth = end.ou - start.ou;
+            activity.intervals.push(GcInterval {
+                start_time,
+                end_time,
+                young_gc: ygc,
+                young_gc_per_minute: ygc * 60.0 / elapsed,
+                young_pause_avg: Self::pause_avg(ygct, ygc),
+                full_gc: fgc,
+                full_gc_per_minute: fgc * 60.0 / elapsed,
+                full_pause_avg: Self::pause_avg(fgct, fgc),
+                gc_time_ratio: gct * 100.0 / elapsed,
+                old_used: end.ou,
+                old_growth_per_minute: old_growth * 60.0 / elapsed,
+            });
+            seconds += elapsed;
+            young_gc += ygc;
+            young_time += ygct;
+            full_gc += fgc;
+            full_time += fgct;
+            gc_time += gct;
+            if fgc == 0.0 {
+                growth += old_growth;
+                growth_seconds += elapsed;
+            }
+        }
+        if seconds > 0.0 {
+            activity.young_gc_per_minute = young_gc * 60.0 / seconds;
+            activity.young_pause_avg = Self::pause_avg(young_time, young_gc);
+            activity.full_gc_per_minute = full_gc * 60.0 / seconds;
+            activity.full_pause_avg = Self::pause_avg(full_time, full_gc);
+            activity.gc_time_ratio = gc_time * 100.0 / seconds;
         }
-        if flag {
-            let mem_info = MemoryPercent::new(current_time, &line);
-            memory_info.push(mem_info);
-            if cycle.is_some() && current_time.is_some() {
-                current_time = current_time.unwrap().checked_add_signed(Duration::seconds(cycle.unwrap().into()));    
+        if growth_seconds > 0.0 {
+            activity.old_growth_per_minute = growth * 60.0 / growth_seconds;
+        }
+        if let Some((time, last)) = samples.last() {
+            activity.old_used = last.ou;
+            activity.old_capacity = if last.ogcmx > 0.0 { last.ogcmx } else { last.oc };
+            if activity.old_growth_per_minute > 0.0 {
+                let remaining = (activity.old_capacity - activity.old_used).max(0.0);
+                let seconds = remaining * 60.0 / activity.old_growth_per_minute;
+                activity.seconds_to_old_exhaustion = Some(seconds);
+                activity.old_exhaustion_time = time.checked_add_signed(Duration::milliseconds((seconds * 1000.0) as i64));
             }
-            flag = false;
         }
+        activity
+    }
+
+    /// 平均每次GC的暂停时间（毫秒）
+    fn pause_avg(time: f64, count: f64) -> f64 {
+        if count > 0.0 { time * 1000.0 / count } else { 0.0 }
     }
-    memory_info
 }
 
+pub static TIMESTAMP: &str = "Timestamp";
 pub static S0: &str = "S0";
+pub static S1: &str = "S1";
+pub static E: &str = "E";
+pub static O: &str = "O";
+pub static M: &str = "M";
+pub static CCS: &str = "CCS";
 pub static S0C: &str = "S0C";
 pub static S1C: &str = "S1C";
 pub static S0U: &str = "S0U";
@@ -185,27 +530,179 @@ pub mod tests {
             Ok(time) => Some(time),
             Err(_err) => None,
         };
-        let mut flag = false;
+        let mut header = "";
         let mut result: Vec<MemoryValue> = Vec::new();
         for line in lines {
             if line.starts_with("S0C") {
-                flag = true;
+                header = line;
                 continue;
             }
-            if flag {
-                let info: MemoryValue = MemoryValue::new(time, "", line);
+            if !header.is_empty() {
+                let info: MemoryValue = MemoryValue::new(time, "", header, line);
                 result.push(info);
-                flag = false;
+                header = "";
             }
         }
         let memory = vec![MemoryValue {
             file_id:"".to_string(),
             time,
-            value: vec![
-                0.0, 786432.0, 0.0, 786432.0, 5820416.0, 625664.0, 24850432.0, 4080638.3, 236180.0,
-                225812.0, 26332.0, 24429.3, 16.0, 6.146, 0.0, 0.000, 6.146,
-            ],
+            s1c: 786432.0,
+            s1u: 786432.0,
+            ec: 5820416.0,
+            eu: 625664.0,
+            oc: 24850432.0,
+            ou: 4080638.3,
+            mc: 236180.0,
+            mu: 225812.0,
+            ccsc: 26332.0,
+            ccsu: 24429.3,
+            ygc: 16.0,
+            ygct: 6.146,
+            gct: 6.146,
+            ..Default::default()
         }];
         assert_eq!(result, memory);
     }
+
+    #[test]
+    pub fn test_gc_columns() {
+        let content = "  S0     S1     E      O      M     CCS    YGC     YGCT    FGC    FGCT    CGC    CGCT     GCT
+  0.00 100.00  10.38  16.41  95.61  92.77     16    6.146     0    0.000     2    0.010    6.156
+ S0C    S1C    S0U    S1U      EC       EU        OC         OU       MC     MU    CCSC   CCSU   YGC     YGCT    FGC    FGCT    CGC    CGCT     GCT
+ 0.0   786432.0  0.0   786432.0 5820416.0 625664.0 24850432.0 4080638.3  236180.0 225812.0 26332.0 24429.3     16    6.146   0      0.000     2    0.010    6.156
+ NGCMN    NGCMX     NGC     S0C   S1C       EC      OGCMN      OGCMX       OGC         OC       MCMN     MCMX      MC     CCSMN    CCSMX     CCSC    YGC    FGC   CGC   ZZZ
+     0.0 33554432.0 6606848.0    0.0 786432.0 5820416.0        0.0 33554432.0 24850432.0 24850432.0        0.0 1267712.0 236180.0        0.0 1048576.0  26332.0     16     0     2   7.5
+";
+        let (value, percent) = create("jstat/20240809_170136/gc_17606.log", "f", content.as_bytes()).unwrap();
+        let value = value.unwrap();
+        assert_eq!(value.time, NaiveDateTime::parse_from_str("20240809_170136", "%Y%m%d_%H%M%S").ok());
+        assert_eq!((value.s0u, value.s1c, value.ou), (0.0, 786432.0, 4080638.3));
+        assert_eq!((value.cgc, value.cgct, value.gct), (2.0, 0.010, 6.156));
+        assert_eq!((value.ngcmx, value.ngc, value.ogcmx, value.mcmx, value.ccsmx), (33554432.0, 6606848.0, 33554432.0, 1267712.0, 1048576.0));
+        assert_eq!(value.extras.get("ZZZ"), Some(&7.5));
+        assert_eq!(percent.unwrap().cgc, 2.0);
+    }
+
+    #[test]
+    pub fn test_gc_util() {
+        let jdk8 = MemoryPercent::new(None, "f", "  S0     S1     E      O      M     CCS    YGC     YGCT    FGC    FGCT     GCT   ",
+            "  0.00 100.00  10.38  16.41  95.61  92.77     16    6.146     0    0.000    6.146");
+        assert_eq!((jdk8.s1, jdk8.e, jdk8.o, jdk8.m, jdk8.ccs), (100.0, 10.38, 16.41, 95.61, 92.77));
+        assert_eq!((jdk8.ygc, jdk8.fgc, jdk8.cgc, jdk8.gct), (16.0, 0.0, 0.0, 6.146));
+        let jdk11 = MemoryPercent::new(None, "f", "  S0     S1     E      O      M     CCS    YGC     YGCT    FGC    FGCT    CGC    CGCT     GCT   ",
+            "     -      -  10.38  16.41  95.61  92.77     16    6.146     1    0.200     4    0.010    6.356");
+        assert_eq!((jdk11.s0, jdk11.fgc, jdk11.cgc, jdk11.gct), (0.0, 1.0, 4.0, 6.356));
+
+        let content = "Timestamp         S0     S1     E      O      M     CCS    YGC     YGCT    FGC    FGCT     GCT
+         100.5   0.00 100.00  10.38  16.41  95.61  92.77     16    6.146     0    0.000    6.146
+         105.5   0.00 100.00  55.02  16.41  95.61  92.77     16    6.146     0    0.000    6.146
+ S0C    S1C    S0U    S1U      EC       EU        OC         OU       MC     MU    CCSC   CCSU   YGC     YGCT    FGC    FGCT     GCT
+ 0.0   786432.0  0.0   786432.0 5820416.0 625664.0 24850432.0 4080638.3  236180.0 225812.0 26332.0 24429.3     16    6.146   0      0.000    6.146
+";
+        let start = NaiveDateTime::parse_from_str("20240809_170136", "%Y%m%d_%H%M%S").ok();
+        let samples = batch_crate_memory_percent(content.as_bytes(), "f", start, None).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].time, start);
+        assert_eq!(samples[1].time, start.map(|t| t + Duration::seconds(5)));
+        assert_eq!(samples[1].e, 55.02);
+
+        // 没有-t列时按周期递增，没有周期时只有第一行有采集时间
+        let content = "  S0     S1     E      O      M     CCS    YGC     YGCT    FGC    FGCT     GCT
+  0.00 100.00  10.38  16.41  95.61  92.77     16    6.146     0    0.000    6.146
+  0.00 100.00  55.02  16.41  95.61  92.77     16    6.146     0    0.000    6.146
+";
+        let samples = batch_crate_memory_percent(content.as_bytes(), "f", start, Some(10)).unwrap();
+        assert_eq!(samples[1].time, start.map(|t| t + Duration::seconds(10)));
+        let samples = batch_crate_memory_percent(content.as_bytes(), "f", start, None).unwrap();
+        assert_eq!((samples[0].time, samples[1].time), (start, None));
+    }
+
+    #[test]
+    pub fn test_memory_percent_series() {
+        let sample = |source: &str, minute: i64, o: f64| {
+            let percent = MemoryPercent {
+                time: NaiveDateTime::parse_from_str("20240809_170000", "%Y%m%d_%H%M%S").ok().map(|t| t + Duration::minutes(minute)),
+                o,
+                ..Default::default()
+            };
+            DBMemoryPercentSource { percent: DBMemoryPercent::new(&percent, "w"), source: source.to_string() }
+        };
+        let samples = vec![
+            sample("cap/20240809_170000/gcutil_100.log", 0, 10.0),
+            sample("cap/20240809_170000/gcutil_200.log", 0, 80.0),
+            sample("cap/20240809_170100/gcutil_100.log", 1, 20.0),
+        ];
+        let series = MemoryPercentCount::group(samples);
+        assert_eq!(series.len(), 2);
+        assert_eq!((series[0].jvm.as_str(), series[0].periodic, series[0].o.clone()), ("100", false, vec![10.0, 20.0]));
+        assert_eq!((series[1].jvm.as_str(), series[1].o.clone()), ("200", vec![80.0]));
+    }
+
+    #[test]
+    pub fn test_gc_activity() {
+        let sample = |minute: i64, ygc: f64, ygct: f64, fgc: f64, fgct: f64, ou: f64| {
+            let memory = MemoryValue {
+                time: NaiveDateTime::parse_from_str("20240809_170000", "%Y%m%d_%H%M%S").ok().map(|t| t + Duration::minutes(minute)),
+                ygc,
+                ygct,
+                fgc,
+                fgct,
+                gct: ygct + fgct,
+                ou,
+                oc: 10000.0,
+                ogcmx: 20000.0,
+                ..Default::default()
+            };
+            DBMemory::new(&memory, "w")
+        };
+        let samples = vec![
+            sample(0, 10.0, 1.0, 0.0, 0.0, 1000.0),
+            sample(1, 16.0, 1.3, 0.0, 0.0, 2000.0),
+            sample(2, 20.0, 1.5, 1.0, 2.0, 500.0),
+            sample(3, 22.0, 1.6, 1.0, 2.0, 1500.0),
+            // JVM重启后计数器归零
+            sample(4, 1.0, 0.1, 0.0, 0.0, 100.0),
+        ];
+        let activity = GcActivity::new(&samples);
+        assert_eq!(activity.intervals.len(), 3);
+        assert_eq!(activity.intervals[0].young_gc_per_minute, 6.0);
+        assert!((activity.intervals[0].young_pause_avg - 50.0).abs() < 1e-6);
+        assert_eq!(activity.intervals[1].full_pause_avg, 2000.0);
+        assert_eq!(activity.intervals[1].old_growth_per_minute, -1500.0);
+        assert_eq!(activity.young_gc_per_minute, 4.0);
+        assert_eq!(activity.full_gc_per_minute, 1.0 / 3.0);
+        assert_eq!(activity.old_growth_per_minute, 1000.0);
+        assert_eq!(activity.old_used, 100.0);
+        assert_eq!(activity.old_capacity, 20000.0);
+        assert_eq!(activity.seconds_to_old_exhaustion, Some(19900.0 * 60.0 / 1000.0));
+        assert!(GcActivity::new(&[]).intervals.is_empty());
+    }
+
+    #[test]
+    pub fn test_gc_series() {
+        let sample = |source: &str, minute: i64, ygc: f64| {
+            let memory = MemoryValue {
+                time: NaiveDateTime::parse_from_str("20240809_170000", "%Y%m%d_%H%M%S").ok().map(|t| t + Duration::minutes(minute)),
+                ygc,
+                ..Default::default()
+            };
+            DBMemorySource { memory: DBMemory::new(&memory, "w"), source: source.to_string() }
+        };
+        // 两个JVM的采样交替出现，各自的计数器差值才是真实的GC次数
+        let samples = vec![
+            sample("cap/gc/gc_100.log", 0, 10.0),
+            sample("cap/gc/gc_200.log", 0, 500.0),
+            sample("cap/gc/gc_100.log", 1, 16.0),
+            sample("cap/gc/gc_200.log", 1, 530.0),
+            sample("cap/20240809_170000/gc_100.log", 2, 20.0),
+        ];
+        let series = GcSeries::group(samples);
+        assert_eq!(series.len(), 3);
+        assert_eq!((series[0].jvm.as_str(), series[0].periodic), ("100", false));
+        assert!(series[0].activity.intervals.is_empty());
+        assert_eq!((series[1].jvm.as_str(), series[1].periodic), ("100", true));
+        assert_eq!(series[1].activity.young_gc_per_minute, 6.0);
+        assert_eq!((series[2].jvm.as_str(), series[2].periodic), ("200", true));
+        assert_eq!(series[2].activity.young_gc_per_minute, 30.0);
+    }
 }
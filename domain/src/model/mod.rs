@@ -1,4 +1,8 @@
 pub mod cpu;
 pub mod memory;
 pub mod stack;
-pub mod thread;
\ No newline at end of file
+pub mod thread;
+pub mod lock;
+pub mod timeline;
+pub mod diff;
+pub mod correlated;
@@ -1,8 +1,101 @@
 use std::{collections::{hash_map::Entry, HashMap}, sync::Arc};
 
+use actix_web::web;
+use chrono::NaiveDateTime;
 use indexer::cache::pool::StringPool;
+use serde::{Deserialize, Serialize};
 
-use crate::model::thread::{CallFrame, Frame, Thread};
+use crate::model::{lock::{format_address, LockAction}, thread::{CallFrame, Frame, Thread, ThreadStatus}};
+
+/// 火焰图的线程过滤条件，全部为空时使用缓存的调用树
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct FlameQuery {
+    pub status: Option<ThreadStatus>,
+    /// 线程名前缀
+    pub prefix: Option<String>,
+    pub start_time: Option<NaiveDateTime>,
+    pub end_time: Option<NaiveDateTime>,
+}
+
+impl FlameQuery {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_none()
+            && self.prefix.as_deref().is_none_or(str::is_empty)
+            && self.start_time.is_none()
+            && self.end_time.is_none()
+    }
+
+    /// 过滤条件的缓存键，相同条件得到相同的键
+    pub fn cache_key(&self) -> String {
+        format!(
+            "{:?}|{}|{:?}|{:?}",
+            self.status,
+            self.prefix.as_deref().unwrap_or_default(),
+            self.start_time,
+            self.end_time
+        )
+    }
+}
+
+/// d3-flamegraph格式的节点，value为包含子节点在内的采样数
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FlameNode {
+    pub name: String,
+    pub value: u32,
+    pub children: Vec<FlameNode>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CallTreeQuery {
+    pub work_space_id: String,
+    /// 节点ID，如`0.2.1`
+    pub id: Option<String>,
+    /// 从根节点开始的方法签名路径，未指定id时使用
+    pub path: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub filter: FlameQuery,
+}
+
+impl From<web::Json<CallTreeQuery>> for CallTreeQuery {
+    fn from(query: web::Json<CallTreeQuery>) -> Self {
+        query.into_inner()
+    }
+}
+
+/// 经过节点的线程及其出现的采集时间
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NodeThread {
+    pub name: String,
+    pub times: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CallTreeChild {
+    pub id: String,
+    pub name: String,
+    pub samples: u32,
+    pub has_children: bool,
+}
+
+/// 调用树中的单个节点，只携带直接子节点的摘要，子节点需要再次按ID查询
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CallTreeNode {
+    pub id: String,
+    pub name: String,
+    pub samples: u32,
+    pub threads: Vec<NodeThread>,
+    pub locks: Vec<NodeLock>,
+    pub children: Vec<CallTreeChild>,
+}
+
+/// 线程执行到该方法时持有或等待的锁，samples为出现该锁的线程采样数
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct NodeLock {
+    pub address: String,
+    pub class_name: String,
+    pub action: LockAction,
+    pub samples: u32,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct CallTree {
@@ -10,15 +103,23 @@ pub struct CallTree {
     samples: u32,
     threads: Vec<Arc<str>>,
     times: Vec<Arc<str>>,
+    locks: Vec<NodeLock>,
     next: Option<Vec<Arc<CallTree>>>,
 }
 
 impl CallTree {
     pub fn new(threads: Vec<Thread>) -> Vec<Arc<CallTree>> {
+        Self::from_samples(threads.into_iter().map(|thread| (thread, None)).collect())
+    }
+
+    /// 根据线程及其所属dump的采集时间构建调用树，节点上的threads和times一一对应，
+    /// 每个经过该节点的线程采样记录一次
+    pub fn from_samples(samples: Vec<(Thread, Option<NaiveDateTime>)>) -> Vec<Arc<CallTree>> {
         let mut root: HashMap<Arc<str>, Arc<CallTree>> = HashMap::new();
-        for thread in threads {
+        for (thread, time) in samples {
             if !thread.frames.is_empty() {
-                Self::convert_to_call_tree(thread, &mut root);
+                let time = time.map(|t| t.to_string()).unwrap_or_default();
+                Self::convert_to_call_tree(thread, StringPool::get_arc_str(&time), &mut root);
             }
         }
         root.into_values().collect()
@@ -26,40 +127,121 @@ impl CallTree {
 
     /// 将单个线程的调用帧插入到调用树根节点集合中。
     /// 如果根节点已存在则合并采样，否则新建根节点。
-    fn convert_to_call_tree(thread: Thread, root: &mut HashMap<Arc<str>, Arc<CallTree>>) {
+    fn convert_to_call_tree(thread: Thread, time: Arc<str>, root: &mut HashMap<Arc<str>, Arc<CallTree>>) {
+        let thread_name = StringPool::get_arc_str(&thread.name);
         let mut frames: Vec<CallFrame> = thread.frames;
-        if let Some(root_frame) = frames.pop() {
-            if let Frame::MethodCall = &root_frame.frame {
-                let method_name = StringPool::get_arc_str(root_frame.signature.unwrap().as_str());
-                let threads: Vec<Arc<str>> = vec![]; // 线程名集合（可扩展）
-                let times: Vec<Arc<str>> = vec![];   // 时间点集合（可扩展）
-                // 先分离借用，避免可变借用冲突
-                match root.entry(method_name.clone()) { // 查找或插入根节点
-                    Entry::Occupied(mut e) => {
-                        Self::build_tree_from_frames(frames, Arc::make_mut(e.get_mut()));
-                    }
-                    Entry::Vacant(v) => {
-                        let arc = Arc::new(CallTree {
-                            method_name: method_name.clone(),
-                            samples: 0,
-                            threads: threads.clone(),
-                            times: times.clone(),
-                            next: None,
-                        });
-                        Self::build_tree_from_frames(frames, Arc::make_mut(v.insert(arc)));
+        let mut locks = Vec::new();
+        if let Some(method_name) = Self::next_method(&mut frames, &mut locks) {
+            let sample = (thread_name, time);
+            // 先分离借用，避免可变借用冲突
+            match root.entry(method_name.clone()) { // 查找或插入根节点
+                Entry::Occupied(mut e) => {
+                    Self::build_tree_from_frames(frames, &sample, locks, Arc::make_mut(e.get_mut()));
+                }
+                Entry::Vacant(v) => {
+                    let arc = Arc::new(CallTree {
+                        method_name: method_name.clone(),
+                        samples: 0,
+                        threads: vec![],
+                        times: vec![],
+                        locks: vec![],
+                        next: None,
+                    });
+                    Self::build_tree_from_frames(frames, &sample, locks, Arc::make_mut(v.insert(arc)));
+                }
+            }
+        }
+    }
+
+    /// 从栈底开始弹出下一个方法帧（包括本地方法）。
+    /// 线程dump中锁相关的行紧跟在所属方法之后，因此从栈底倒序遍历时，
+    /// 在方法帧之前弹出的锁帧都属于该方法，收集到`locks`中
+    fn next_method(frames: &mut Vec<CallFrame>, locks: &mut Vec<(u64, LockAction, String)>) -> Option<Arc<str>> {
+        while let Some(frame) = frames.pop() {
+            match frame.frame {
+                Frame::MethodCall | Frame::NativeMethod => {
+                    return frame.signature.map(|signature| StringPool::get_arc_str(&signature));
+                }
+                _ => {
+                    if let Some((address, action)) = LockAction::from_frame(&frame.frame) {
+                        locks.push((address, action, frame.class_name));
                     }
                 }
             }
         }
+        None
     }
 
-    fn build_tree_from_frames(mut frames: Vec<CallFrame>, parent_node: &mut CallTree) {
-        parent_node.samples += 1;
-        if let Some(frame) = frames.pop() {
-            if !matches!(frame.frame, Frame::MethodCall) {
-               return;
+    fn add_locks(&mut self, locks: Vec<(u64, LockAction, String)>) {
+        for (address, action, class_name) in locks {
+            let address = format_address(address);
+            match self.locks.iter_mut().find(|lock| lock.address == address && lock.action == action) {
+                Some(lock) => lock.samples += 1,
+                None => self.locks.push(NodeLock { address, class_name, action, samples: 1 }),
             }
-             let method_name = StringPool::get_arc_str(frame.signature.unwrap().as_str());
+        }
+    }
+
+    /// 转换为d3-flamegraph格式，多个根节点挂在名为`root`的虚拟节点下
+    pub fn to_flame_graph(roots: &[Arc<CallTree>]) -> FlameNode {
+        let children: Vec<FlameNode> = Self::sorted(roots).into_iter().map(|node| node.flame_node()).collect();
+        FlameNode {
+            name: "root".to_string(),
+            value: children.iter().map(|child| child.value).sum(),
+            children,
+        }
+    }
+
+    /// 转换为Brendan Gregg的folded格式，每行为`根方法;...;栈顶方法 自身采样数`
+    pub fn to_folded(roots: &[Arc<CallTree>]) -> String {
+        let mut lines = Vec::new();
+        let mut path = Vec::new();
+        for node in Self::sorted(roots) {
+            node.fold(&mut path, &mut lines);
+        }
+        lines.join("\n")
+    }
+
+    fn flame_node(&self) -> FlameNode {
+        FlameNode {
+            name: self.method_name.to_string(),
+            value: self.samples,
+            children: self
+                .next
+                .as_deref()
+                .map(|next| Self::sorted(next).into_iter().map(|node| node.flame_node()).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn fold<'a>(&'a self, path: &mut Vec<&'a str>, lines: &mut Vec<String>) {
+        path.push(&self.method_name);
+        let next = self.next.as_deref().unwrap_or_default();
+        let child_samples: u32 = next.iter().map(|node| node.samples).sum();
+        let self_samples = self.samples.saturating_sub(child_samples);
+        if self_samples > 0 {
+            lines.push(format!("{} {}", path.join(";"), self_samples));
+        }
+        for node in Self::sorted(next) {
+            node.fold(path, lines);
+        }
+        path.pop();
+    }
+
+    // 按方法名排序，保证输出稳定
+    fn sorted(nodes: &[Arc<CallTree>]) -> Vec<&CallTree> {
+        let mut nodes: Vec<&CallTree> = nodes.iter().map(|node| node.as_ref()).collect();
+        nodes.sort_by(|a, b| a.method_name.cmp(&b.method_name));
+        nodes
+    }
+
+    fn build_tree_from_frames(mut frames: Vec<CallFrame>, sample: &(Arc<str>, Arc<str>), locks: Vec<(u64, LockAction, String)>, parent_node: &mut CallTree) {
+        parent_node.samples += 1;
+        parent_node.threads.push(sample.0.clone());
+        parent_node.times.push(sample.1.clone());
+        parent_node.add_locks(locks);
+        let mut next_locks = Vec::new();
+        if let Some(method_name) = Self::next_method(&mut frames, &mut next_locks) {
                 let next_nodes = parent_node.next.get_or_insert_with(Vec::new);
                 // 查找下一个节点是否已存在（同名方法）
                 let mut found_idx = None;
@@ -73,23 +255,100 @@ impl CallTree {
                 if let Some(idx) = found_idx {
                     // 已存在该方法节点，递归进入并累加采样
                     let mut_node = Arc::make_mut(&mut next_nodes[idx]);
-                    Self::build_tree_from_frames(frames, mut_node);
+                    Self::build_tree_from_frames(frames, sample, next_locks, mut_node);
                 } else {
                     // 不存在则新建节点，采样数初始为0，递归会+1
                     let mut new_node = CallTree {
                         method_name: method_name.clone(), // 方法名
                         samples: 0, // 递归会+1
-                        threads: vec![], // 可根据需要填充
-                        times: vec![],   // 可根据需要填充
+                        threads: vec![], // 经过该节点的线程，递归时填充
+                        times: vec![],   // 与threads一一对应的采集时间
+                        locks: vec![],   // 执行到该方法时持有或等待的锁
                         next: None,      // 子节点
                     };
                     // 递归构建子路径
-                    Self::build_tree_from_frames(frames, &mut new_node);
+                    Self::build_tree_from_frames(frames, sample, next_locks, &mut new_node);
                     // 新节点加入当前节点的子节点列表
                     next_nodes.push(Arc::new(new_node));
                 }
         }
     }
+
+    /// 按节点ID或方法路径查找节点，只返回该节点的直接子节点。
+    /// 节点ID由各层子节点（按方法名排序）的下标以`.`连接而成，ID和路径都为空时返回虚拟根节点
+    pub fn find_node(roots: &[Arc<CallTree>], id: Option<&str>, path: Option<&[String]>) -> Option<CallTreeNode> {
+        let steps: Vec<Step> = match (id.filter(|id| !id.is_empty()), path) {
+            (Some(id), _) => id
+                .split('.')
+                .map(|idx| idx.parse().ok().map(Step::Index))
+                .collect::<Option<_>>()?,
+            (None, Some(path)) => path.iter().map(|name| Step::Name(name)).collect(),
+            (None, None) => vec![],
+        };
+        let mut level = Self::sorted(roots);
+        let mut node: Option<&CallTree> = None;
+        let mut ids: Vec<String> = Vec::new();
+        for step in steps {
+            let idx = match step {
+                Step::Index(idx) => idx,
+                Step::Name(name) => level.iter().position(|n| &*n.method_name == name)?,
+            };
+            let current = *level.get(idx)?;
+            ids.push(idx.to_string());
+            level = Self::sorted(current.next.as_deref().unwrap_or_default());
+            node = Some(current);
+        }
+        let id = ids.join(".");
+        let children = level
+            .iter()
+            .enumerate()
+            .map(|(idx, child)| CallTreeChild {
+                id: if id.is_empty() { idx.to_string() } else { format!("{}.{}", id, idx) },
+                name: child.method_name.to_string(),
+                samples: child.samples,
+                has_children: child.next.as_ref().is_some_and(|next| !next.is_empty()),
+            })
+            .collect();
+        Some(match node {
+            Some(node) => CallTreeNode {
+                id,
+                name: node.method_name.to_string(),
+                samples: node.samples,
+                threads: node.node_threads(),
+                locks: node.locks.clone(),
+                children,
+            },
+            None => CallTreeNode {
+                id,
+                name: "root".to_string(),
+                samples: roots.iter().map(|node| node.samples).sum(),
+                threads: vec![],
+                locks: vec![],
+                children,
+            },
+        })
+    }
+
+    // 按线程名归并采样，保留首次出现的顺序
+    fn node_threads(&self) -> Vec<NodeThread> {
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut threads: Vec<NodeThread> = Vec::new();
+        for (name, time) in self.threads.iter().zip(self.times.iter()) {
+            let idx = *index.entry(name).or_insert_with(|| {
+                threads.push(NodeThread { name: name.to_string(), times: vec![] });
+                threads.len() - 1
+            });
+            if !time.is_empty() {
+                threads[idx].times.push(time.to_string());
+            }
+        }
+        threads
+    }
+}
+
+enum Step<'a> {
+    Index(usize),
+    Name(&'a str),
 }
 
 #[cfg(test)]
@@ -101,6 +360,121 @@ pub mod tests {
     use chrono::Local;
     use super::*;
 
+    #[test]
+    pub fn test_stored_threads() {
+        let lines: Vec<String> = vec![
+          "\"Thread-1\" #1 prio=5 os_prio=0 tid=0x00007f3d70001800 nid=0x2f03 runnable [0x00007f3d80f21000]",
+          "java.lang.Thread.State: RUNNABLE",
+          "at com.example.MyClass.myMethod(MyClass.java:10)",
+          "at com.example.MyClass.run(MyClass.java:5)",
+          "at java.lang.Thread.run(Thread.java:748)",
+        ].into_iter().map(|l| l.to_string()).collect();
+        let thread = Thread::new(&lines, 0, 0).unwrap();
+        let stored = crate::db::db_thread::DBThreadInfo::new(&thread, "file").to_thread();
+        let tree = CallTree::new(vec![stored.clone(), stored]);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(&*tree[0].method_name, "java.lang.Thread.run(Thread.java:748)");
+        assert_eq!(tree[0].samples, 2);
+        let child = &tree[0].next.as_ref().unwrap()[0];
+        assert_eq!(&*child.method_name, "com.example.MyClass.run(MyClass.java:5)");
+        assert_eq!(child.samples, 2);
+    }
+
+    #[test]
+    pub fn test_flame_graph() {
+        let thread = |frames: &[&str]| {
+            let mut lines = vec![
+                "\"Thread-1\" #1 prio=5 os_prio=0 tid=0x00007f3d70001800 nid=0x2f03 runnable [0x00007f3d80f21000]".to_string(),
+                "java.lang.Thread.State: RUNNABLE".to_string(),
+            ];
+            lines.extend(frames.iter().map(|f| f.to_string()));
+            Thread::new(&lines, 0, 0).unwrap()
+        };
+        let tree = CallTree::new(vec![
+            thread(&["at com.example.A.read(A.java:3)", "at com.example.A.run(A.java:1)"]),
+            thread(&["at com.example.A.write(A.java:4)", "at com.example.A.run(A.java:1)"]),
+            thread(&["at com.example.A.run(A.java:1)"]),
+        ]);
+        let flame = CallTree::to_flame_graph(&tree);
+        assert_eq!(flame.value, 3);
+        assert_eq!(flame.children[0].name, "com.example.A.run(A.java:1)");
+        assert_eq!(flame.children[0].value, 3);
+        assert_eq!(flame.children[0].children.len(), 2);
+        assert_eq!(
+            CallTree::to_folded(&tree),
+            "com.example.A.run(A.java:1) 1\n\
+             com.example.A.run(A.java:1);com.example.A.read(A.java:3) 1\n\
+             com.example.A.run(A.java:1);com.example.A.write(A.java:4) 1"
+        );
+    }
+
+    #[test]
+    pub fn test_find_node() {
+        let thread = |name: &str, frames: &[&str]| {
+            let mut lines = vec![
+                format!("\"{}\" #1 prio=5 os_prio=0 tid=0x00007f3d70001800 nid=0x2f03 runnable [0x00007f3d80f21000]", name),
+                "java.lang.Thread.State: RUNNABLE".to_string(),
+            ];
+            lines.extend(frames.iter().map(|f| f.to_string()));
+            Thread::new(&lines, 0, 0).unwrap()
+        };
+        let time = |minute| chrono::NaiveDate::from_ymd_opt(2024, 8, 9).and_then(|d| d.and_hms_opt(17, minute, 0));
+        let tree = CallTree::from_samples(vec![
+            (thread("worker-1", &["at com.example.A.read(A.java:3)", "at com.example.A.run(A.java:1)"]), time(1)),
+            (thread("worker-2", &["at com.example.A.write(A.java:4)", "at com.example.A.run(A.java:1)"]), time(1)),
+            (thread("worker-1", &["at com.example.A.read(A.java:3)", "at com.example.A.run(A.java:1)"]), time(2)),
+        ]);
+        let root = CallTree::find_node(&tree, None, None).unwrap();
+        assert_eq!((root.id.as_str(), root.samples), ("", 3));
+        assert_eq!(root.children.len(), 1);
+        assert!(root.children[0].has_children);
+
+        let run = CallTree::find_node(&tree, Some("0"), None).unwrap();
+        assert_eq!(run.name, "com.example.A.run(A.java:1)");
+        assert_eq!(run.threads.len(), 2);
+        assert_eq!(run.children.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), vec!["0.0", "0.1"]);
+
+        let path = vec!["com.example.A.run(A.java:1)".to_string(), "com.example.A.read(A.java:3)".to_string()];
+        let read = CallTree::find_node(&tree, None, Some(&path)).unwrap();
+        assert_eq!(read, CallTree::find_node(&tree, Some("0.0"), None).unwrap());
+        assert_eq!(read.samples, 2);
+        assert_eq!(read.threads, vec![NodeThread {
+            name: "worker-1".to_string(),
+            times: vec!["2024-08-09 17:01:00".to_string(), "2024-08-09 17:02:00".to_string()],
+        }]);
+        assert!(read.children.is_empty());
+        assert!(CallTree::find_node(&tree, Some("0.5"), None).is_none());
+    }
+
+    #[test]
+    pub fn test_lock_frames() {
+        let lines: Vec<String> = vec![
+          "\"worker-1\" #1 prio=5 os_prio=0 tid=0x00007f3d70001800 nid=0x2f03 waiting for monitor entry [0x00007f3d80f21000]",
+          "java.lang.Thread.State: BLOCKED (on object monitor)",
+          "at java.lang.Object.wait(Native Method)",
+          "- waiting on <0x00000000d5a6f9c8> (a java.lang.Object)",
+          "at com.example.Cache.load(Cache.java:20)",
+          "- locked <0x00000000d5a6f9b0> (a com.example.Cache)",
+          "at com.example.Cache.get(Cache.java:10)",
+          "at java.lang.Thread.run(Thread.java:748)",
+        ].into_iter().map(|l| l.to_string()).collect();
+        let thread = Thread::new(&lines, 0, 0).unwrap();
+        let tree = CallTree::new(vec![thread.clone(), thread]);
+        let load = CallTree::find_node(&tree, Some("0.0.0"), None).unwrap();
+        assert_eq!(load.name, "com.example.Cache.load(Cache.java:20)");
+        assert_eq!(load.locks, vec![NodeLock {
+            address: "0x00000000d5a6f9b0".to_string(),
+            class_name: "com.example.Cache".to_string(),
+            action: LockAction::Locked,
+            samples: 2,
+        }]);
+        let wait = CallTree::find_node(&tree, Some("0.0.0.0"), None).unwrap();
+        assert_eq!(wait.name, "java.lang.Object.wait(Native Method)");
+        assert_eq!(wait.samples, 2);
+        assert_eq!(wait.locks[0].action, LockAction::WaitingOn);
+        assert!(CallTree::find_node(&tree, Some("0.0"), None).unwrap().locks.is_empty());
+    }
+
     #[test]
     pub fn test() {
         let real_start = Local::now();
@@ -1107,4 +1107,33 @@ pub mod tests {
         };
         assert_eq!(result.unwrap(), thread)
     }
+    #[test]
+    pub fn test_stack_info_round_trip() {
+        let lines = vec![
+          "\"Thread-2\" #2 prio=5 os_prio=0 tid=0x00007f3d70002800 nid=0x2f04 waiting for monitor entry [0x00007f3d80f22000]".to_string(),
+          "java.lang.Thread.State: BLOCKED (on object monitor)".to_string(),
+          "at com.example.MyClass.synchronizedMethod(MyClass.java:20)".to_string(),
+          "- waiting to lock <0x00000000c7c600d0> (a java.lang.Object)".to_string(),
+          "at java.lang.Object.hashCode(Native Method)".to_string(),
+          "at java.lang.Thread.run(Thread.java:748)".to_string()
+        ];
+        let thread = Thread::new(&lines, 1, 6).unwrap();
+        let info = DBThreadInfo::new(&thread, "file");
+        let restored = info.to_thread();
+        assert_eq!(restored.frames, thread.frames);
+        assert_eq!(restored.status, ThreadStatus::Blocked);
+
+        // 旧格式只保存Frame枚举，需要从源文件重新解析
+        let mut legacy = info.clone();
+        legacy.stack_info = "\"MethodCall\"".to_string();
+        legacy.stack_format = crate::db::db_thread::STACK_FORMAT_FRAME;
+        assert!(legacy.to_thread().frames.is_empty());
+        let mut file_lines = vec!["".to_string()];
+        file_lines.extend(lines.iter().cloned());
+        file_lines.push("".to_string());
+        legacy.start_line = 2;
+        legacy.end_line = 7;
+        legacy.rebuild_stack_info(&file_lines).unwrap();
+        assert_eq!(legacy.to_thread().frames, thread.frames);
+    }
 }
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+
+use actix_web::web;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{db::db_thread::DBThreadSample, model::thread::{Frame, Thread, ThreadStatus}};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TimelineQuery {
+    pub work_space_id: String,
+    pub name: Option<String>,
+    pub nid: Option<String>,
+    pub tid: Option<String>,
+}
+
+impl From<web::Json<TimelineQuery>> for TimelineQuery {
+    fn from(query: web::Json<TimelineQuery>) -> Self {
+        query.into_inner()
+    }
+}
+
+/// 线程在单个dump中的采样
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TimelineSample {
+    pub thread_id: String,
+    pub file_id: String,
+    pub time: Option<NaiveDateTime>,
+    pub status: ThreadStatus,
+    pub top_method: String,
+    /// 与上一次采样相比堆栈是否发生变化，第一次采样为false
+    pub stack_changed: bool,
+    /// 截止到当前采样，堆栈连续保持不变的采样次数（包含当前采样）
+    pub unchanged_count: usize,
+}
+
+/// 同一个线程在多个dump中的状态变化
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ThreadTimeline {
+    pub name: String,
+    pub tid: String,
+    pub nid: String,
+    pub samples: Vec<TimelineSample>,
+    /// 堆栈连续不变的最大采样次数
+    pub max_unchanged: usize,
+}
+
+impl ThreadTimeline {
+    /// 按线程名、tid、nid将采样归并为线程时间线，采样需按采集时间升序排列
+    pub fn build(samples: Vec<DBThreadSample>) -> Vec<ThreadTimeline> {
+        let mut order: Vec<(String, String, String)> = Vec::new();
+        let mut groups: HashMap<(String, String, String), (ThreadTimeline, String)> = HashMap::new();
+        for sample in samples {
+            let key = (sample.thread_name.clone(), sample.tid.clone(), sample.nid.clone());
+            let (timeline, last_stack) = groups.entry(key.clone()).or_insert_with(|| {
+                order.push(key);
+                (
+                    ThreadTimeline {
+                        name: sample.thread_name.clone(),
+                        tid: sample.tid.clone(),
+                        nid: sample.nid.clone(),
+                        samples: vec![],
+                        max_unchanged: 0,
+                    },
+                    String::new(),
+                )
+            });
+            let (stack_changed, unchanged_count) = match timeline.samples.last() {
+                Some(previous) if *last_stack == sample.stack_info => (false, previous.unchanged_count + 1),
+                Some(_) => (true, 1),
+                None => (false, 1),
+            };
+            timeline.max_unchanged = timeline.max_unchanged.max(unchanged_count);
+            timeline.samples.push(TimelineSample {
+                thread_id: sample.id,
+                file_id: sample.file_id,
+                time: sample.exe_time,
+                status: ThreadStatus::try_from(sample.thread_status).unwrap_or(ThreadStatus::Unknown),
+                top_method: sample.top_method,
+                stack_changed,
+                unchanged_count,
+            });
+            *last_stack = sample.stack_info;
+        }
+        order
+            .into_iter()
+            .filter_map(|key| groups.remove(&key).map(|(timeline, _)| timeline))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct StuckQuery {
+    pub threshold: Option<usize>,
+}
+
+/// 单个dump文件中的线程，线程附带THREAD_INFO中的ID
+#[derive(Debug, Clone)]
+pub struct DumpThreads {
+    pub file_id: String,
+    pub time: Option<NaiveDateTime>,
+    pub threads: Vec<(String, Thread)>,
+}
+
+/// 判定为卡住所需的最少连续dump数，只出现在一个dump中的线程无法判断堆栈是否变化
+pub const MIN_STUCK_THRESHOLD: usize = 2;
+
+/// 在连续多个dump中堆栈完全相同且处于RUNNABLE或BLOCKED状态的线程
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct StuckThread {
+    pub name: String,
+    pub tid: String,
+    pub nid: String,
+    pub status: ThreadStatus,
+    pub top_method: String,
+    pub stack: Vec<String>,
+    pub dumps: usize,
+    pub start_time: Option<NaiveDateTime>,
+    pub end_time: Option<NaiveDateTime>,
+    /// 持续时间（秒），根据首末两次采集时间计算
+    pub duration: i64,
+    pub thread_ids: Vec<String>,
+}
+
+struct StuckRun<'a> {
+    last_dump: usize,
+    thread: &'a Thread,
+    start_time: Option<NaiveDateTime>,
+    end_time: Option<NaiveDateTime>,
+    thread_ids: Vec<String>,
+}
+
+impl StuckThread {
+    /// 找出堆栈在至少`threshold`个连续dump中保持不变的线程，dump需按采集时间升序排列
+    /// `threshold`由调用方校验不小于`MIN_STUCK_THRESHOLD`
+    pub fn find(dumps: &[DumpThreads], threshold: usize) -> Vec<StuckThread> {
+        let mut running: HashMap<(&str, &str, &str), StuckRun> = HashMap::new();
+        let mut result = Vec::new();
+        for (dump_idx, dump) in dumps.iter().enumerate() {
+            for (thread_id, thread) in &dump.threads {
+                if thread.frames.is_empty()
+                    || !matches!(thread.status, ThreadStatus::Runnable | ThreadStatus::Blocked)
+                {
+                    continue;
+                }
+                let key = (thread.name.as_str(), thread.tid.as_str(), thread.nid.as_str());
+                let continued = running.get(&key).is_some_and(|run| {
+                    run.last_dump + 1 == dump_idx && run.thread.frames == thread.frames
+                });
+                if continued {
+                    if let Some(run) = running.get_mut(&key) {
+                        run.last_dump = dump_idx;
+                        run.thread = thread;
+                        run.end_time = dump.time;
+                        run.thread_ids.push(thread_id.clone());
+                    }
+                } else if let Some(run) = running.insert(key, StuckRun {
+                    last_dump: dump_idx,
+                    thread,
+                    start_time: dump.time,
+                    end_time: dump.time,
+                    thread_ids: vec![thread_id.clone()],
+                }) {
+                    Self::collect(run, threshold, &mut result);
+                }
+            }
+        }
+        for run in running.into_values() {
+            Self::collect(run, threshold, &mut result);
+        }
+        result.sort_by(|a, b| {
+            b.dumps
+                .cmp(&a.dumps)
+                .then_with(|| b.duration.cmp(&a.duration))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        result
+    }
+
+    fn collect(run: StuckRun, threshold: usize, result: &mut Vec<StuckThread>) {
+        if run.thread_ids.len() < threshold {
+            return;
+        }
+        let thread = run.thread;
+        let duration = match (run.start_time, run.end_time) {
+            (Some(start), Some(end)) => (end - start).num_seconds(),
+            _ => 0,
+        };
+        result.push(StuckThread {
+            name: thread.name.clone(),
+            tid: thread.tid.clone(),
+            nid: thread.nid.clone(),
+            status: thread.status.clone(),
+            top_method: thread
+                .frames
+                .first()
+                .and_then(|frame| frame.signature.clone())
+                .unwrap_or_default(),
+            stack: thread
+                .frames
+                .iter()
+                .filter(|frame| matches!(frame.frame, Frame::MethodCall | Frame::NativeMethod))
+                .filter_map(|frame| frame.signature.clone())
+                .collect(),
+            dumps: run.thread_ids.len(),
+            start_time: run.start_time,
+            end_time: run.end_time,
+            duration,
+            thread_ids: run.thread_ids,
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub fn sample(file: &str, minute: u32, name: &str, status: ThreadStatus, stack: &str) -> DBThreadSample {
+        DBThreadSample {
+            id: format!("{}-{}", file, name),
+            file_id: file.to_string(),
+            exe_time: chrono::NaiveDate::from_ymd_opt(2024, 8, 9)
+                .and_then(|d| d.and_hms_opt(17, minute, 0)),
+            thread_name: name.to_string(),
+            tid: format!("tid-{}", name),
+            nid: format!("nid-{}", name),
+            thread_status: status.into(),
+            top_method: stack.lines().next().unwrap_or_default().to_string(),
+            stack_info: stack.to_string(),
+        }
+    }
+
+    fn dump(minute: u32, threads: Vec<(&str, &[&str])>) -> DumpThreads {
+        DumpThreads {
+            file_id: format!("f{}", minute),
+            time: chrono::NaiveDate::from_ymd_opt(2024, 8, 9)
+                .and_then(|d| d.and_hms_opt(17, minute, 0)),
+            threads: threads
+                .into_iter()
+                .map(|(header, frames)| {
+                    let mut lines = vec![header.to_string()];
+                    lines.extend(frames.iter().map(|f| f.to_string()));
+                    (format!("{}-{}", minute, lines[0].len()), Thread::new(&lines, 0, 0).unwrap())
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    pub fn test_find_stuck_threads() {
+        let busy: &[&str] = &[
+            "java.lang.Thread.State: RUNNABLE",
+            "at java.net.SocketInputStream.socketRead0(Native Method)",
+            "at com.example.Client.read(Client.java:44)",
+        ];
+        let moved: &[&str] = &[
+            "java.lang.Thread.State: RUNNABLE",
+            "at com.example.Client.write(Client.java:50)",
+        ];
+        let parked: &[&str] = &[
+            "java.lang.Thread.State: WAITING (parking)",
+            "at sun.misc.Unsafe.park(Native Method)",
+        ];
+        let reader = "\"reader\" #1 prio=5 os_prio=0 tid=0x0000000000000001 nid=0x1 runnable [0x0000000000000001]";
+        let writer = "\"writer\" #2 prio=5 os_prio=0 tid=0x0000000000000002 nid=0x2 runnable [0x0000000000000002]";
+        let idle = "\"idle\" #3 prio=5 os_prio=0 tid=0x0000000000000003 nid=0x3 waiting on condition [0x0000000000000003]";
+        let dumps = vec![
+            dump(1, vec![(reader, busy), (writer, busy), (idle, parked)]),
+            dump(2, vec![(reader, busy), (writer, moved), (idle, parked)]),
+            dump(4, vec![(reader, busy), (writer, moved), (idle, parked)]),
+        ];
+        let stuck = StuckThread::find(&dumps, 3);
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].name, "reader");
+        assert_eq!(stuck[0].dumps, 3);
+        assert_eq!(stuck[0].duration, 180);
+        assert_eq!(stuck[0].top_method, "java.net.SocketInputStream.socketRead0(Native Method)");
+        assert_eq!(stuck[0].stack.len(), 2);
+
+        let stuck = StuckThread::find(&dumps, 2);
+        assert_eq!(stuck.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["reader", "writer"]);
+        assert_eq!(stuck[1].duration, 120);
+    }
+
+    #[test]
+    pub fn test_build_timeline() {
+        let samples = vec![
+            sample("f1", 1, "worker-1", ThreadStatus::Runnable, "a\nb"),
+            sample("f1", 1, "worker-2", ThreadStatus::Waiting, "c"),
+            sample("f2", 2, "worker-1", ThreadStatus::Runnable, "a\nb"),
+            sample("f3", 3, "worker-1", ThreadStatus::Blocked, "d\nb"),
+            sample("f3", 3, "worker-2", ThreadStatus::Waiting, "c"),
+        ];
+        let timelines = ThreadTimeline::build(samples);
+        assert_eq!(timelines.len(), 2);
+        let worker = &timelines[0];
+        assert_eq!(worker.name, "worker-1");
+        assert_eq!(worker.max_unchanged, 2);
+        let changes: Vec<(bool, usize)> = worker
+            .samples
+            .iter()
+            .map(|s| (s.stack_changed, s.unchanged_count))
+            .collect();
+        assert_eq!(changes, vec![(false, 1), (false, 2), (true, 1)]);
+        assert_eq!(worker.samples[2].status, ThreadStatus::Blocked);
+        assert_eq!(worker.samples[2].top_method, "d");
+        assert_eq!(timelines[1].name, "worker-2");
+        assert_eq!(timelines[1].max_unchanged, 2);
+    }
+}
@@ -33,6 +33,27 @@ impl GlobalCache {
         cache.get_store().contains_key(key)
     }
     
+    /// 删除缓存的数据，数据源变化后下次查询时重新构建
+    pub fn remove(key: &str) {
+        L1_CACHE.lock().unwrap().cache_remove(key);
+        L2_CACHE.lock().unwrap().cache_remove(key);
+    }
+
+    /// 删除以`prefix`开头的全部缓存，用于清理同一个工作空间按不同条件生成的数据
+    pub fn remove_prefix(prefix: &str) {
+        L1_CACHE.lock().unwrap().retain(|key, _| !key.starts_with(prefix));
+        let mut cache = L2_CACHE.lock().unwrap();
+        let keys: Vec<String> = cache.get_store().keys().filter(|key| key.starts_with(prefix)).cloned().collect();
+        for key in keys {
+            cache.cache_remove(&key);
+        }
+    }
+
+    /// 只放入容量有限的L1缓存，适合按查询条件生成、数量不固定的数据
+    pub fn put_hot<T: 'static + Send + Sync>(key: String, value: T) {
+        Self::put_l1(key, value);
+    }
+
     pub fn reset(){
       let mut cache1 = L1_CACHE.lock().unwrap();
       let mut cache2 = L2_CACHE.lock().unwrap();
@@ -75,4 +96,9 @@ impl CacheKey {
       format!("{}::CALL_TREE",work_space_id)
     }
 
+    /// 按线程过滤条件构建的调用树，以`call_tree`为前缀，清理时一起删除
+    pub fn filtered_call_tree(work_space_id: &str, filter: &str) -> String{
+      format!("{}::{}", Self::call_tree(work_space_id), filter)
+    }
+
 }
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Add down migration script here
+DROP TABLE LOCK_INFO;
\ No newline at end of file
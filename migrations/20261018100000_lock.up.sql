@@ -0,0 +1,13 @@
+-- 创建锁信息相关脚本
+CREATE TABLE IF NOT EXISTS LOCK_INFO (
+  ID TEXT PRIMARY KEY,
+  FILE_ID TEXT,
+  THREAD_ID TEXT,
+  FRAME_INDEX INTEGER,
+  ADDRESS TEXT,
+  ACTION INTEGER,
+  CLASS_NAME TEXT
+);
+
+CREATE INDEX IF NOT EXISTS IDX_LOCK_INFO_ADDRESS ON LOCK_INFO (FILE_ID, ADDRESS);
+CREATE INDEX IF NOT EXISTS IDX_LOCK_INFO_THREAD ON LOCK_INFO (THREAD_ID);
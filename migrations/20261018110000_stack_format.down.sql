@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE THREAD_INFO DROP COLUMN STACK_FORMAT;
\ No newline at end of file
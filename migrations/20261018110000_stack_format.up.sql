@@ -0,0 +1,2 @@
+-- 线程堆栈存储格式：0为旧格式（仅Frame枚举），1为完整的CallFrame
+ALTER TABLE THREAD_INFO ADD COLUMN STACK_FORMAT INTEGER DEFAULT 0;
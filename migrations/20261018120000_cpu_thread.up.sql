@@ -0,0 +1,15 @@
+-- 创建top -H线程CPU信息相关脚本
+CREATE TABLE IF NOT EXISTS CPU_THREAD_INFO (
+  ID TEXT PRIMARY KEY,
+  WORKSPACE TEXT,
+  FILE_ID TEXT,
+  PID INTEGER,
+  NID TEXT,
+  USER TEXT,
+  CPU REAL,
+  MEM REAL,
+  CPU_TIME TEXT,
+  COMMAND TEXT
+);
+
+CREATE INDEX IF NOT EXISTS IDX_CPU_THREAD_INFO_WORKSPACE ON CPU_THREAD_INFO (WORKSPACE);
@@ -0,0 +1,15 @@
+-- 创建top进程信息相关脚本
+CREATE TABLE IF NOT EXISTS PROCESS_INFO (
+  ID TEXT PRIMARY KEY,
+  WORKSPACE TEXT,
+  FILE_ID TEXT,
+  EXE_TIME TIMESTAMP,
+  PID INTEGER,
+  USER TEXT,
+  CPU REAL,
+  MEM REAL,
+  CPU_TIME TEXT,
+  COMMAND TEXT
+);
+
+CREATE INDEX IF NOT EXISTS IDX_PROCESS_INFO_WORKSPACE ON PROCESS_INFO (WORKSPACE);
@@ -0,0 +1,13 @@
+-- Add down migration script here
+ALTER TABLE CPU_INFO DROP COLUMN LOAD1;
+ALTER TABLE CPU_INFO DROP COLUMN LOAD5;
+ALTER TABLE CPU_INFO DROP COLUMN LOAD15;
+ALTER TABLE CPU_INFO DROP COLUMN NI;
+ALTER TABLE CPU_INFO DROP COLUMN WA;
+ALTER TABLE CPU_INFO DROP COLUMN HI;
+ALTER TABLE CPU_INFO DROP COLUMN SI;
+ALTER TABLE CPU_INFO DROP COLUMN ST;
+ALTER TABLE CPU_INFO DROP COLUMN BUFF_CACHE;
+ALTER TABLE CPU_INFO DROP COLUMN SWAP_TOTAL;
+ALTER TABLE CPU_INFO DROP COLUMN SWAP_FREE;
+ALTER TABLE CPU_INFO DROP COLUMN SWAP_USED;
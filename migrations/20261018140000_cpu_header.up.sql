@@ -0,0 +1,13 @@
+-- top表头的负载、CPU明细、交换区和缓存
+ALTER TABLE CPU_INFO ADD COLUMN LOAD1 REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN LOAD5 REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN LOAD15 REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN NI REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN WA REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN HI REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN SI REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN ST REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN BUFF_CACHE REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN SWAP_TOTAL REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN SWAP_FREE REAL DEFAULT 0;
+ALTER TABLE CPU_INFO ADD COLUMN SWAP_USED REAL DEFAULT 0;
@@ -0,0 +1,22 @@
+-- 创建jstat -gcutil内存使用率相关脚本
+CREATE TABLE IF NOT EXISTS MEMORY_PERCENT_INFO (
+  ID TEXT PRIMARY KEY,
+  WORKSPACE TEXT,
+  FILE_ID TEXT,
+  S0 REAL,
+  S1 REAL,
+  E REAL,
+  O REAL,
+  M REAL,
+  CCS REAL,
+  YGC REAL,
+  YGCT REAL,
+  FGC REAL,
+  FGCT REAL,
+  CGC REAL,
+  CGCT REAL,
+  GCT REAL,
+  EXE_TIME TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS IDX_MEMORY_PERCENT_INFO_WORKSPACE ON MEMORY_PERCENT_INFO (WORKSPACE);
@@ -0,0 +1,12 @@
+-- Add down migration script here
+ALTER TABLE MEMORY_INFO DROP COLUMN NGCMN;
+ALTER TABLE MEMORY_INFO DROP COLUMN NGCMX;
+ALTER TABLE MEMORY_INFO DROP COLUMN NGC;
+ALTER TABLE MEMORY_INFO DROP COLUMN OGCMN;
+ALTER TABLE MEMORY_INFO DROP COLUMN OGCMX;
+ALTER TABLE MEMORY_INFO DROP COLUMN OGC;
+ALTER TABLE MEMORY_INFO DROP COLUMN MCMN;
+ALTER TABLE MEMORY_INFO DROP COLUMN MCMX;
+ALTER TABLE MEMORY_INFO DROP COLUMN CCSMN;
+ALTER TABLE MEMORY_INFO DROP COLUMN CCSMX;
+ALTER TABLE MEMORY_INFO DROP COLUMN EXTRAS;
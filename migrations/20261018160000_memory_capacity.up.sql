@@ -0,0 +1,12 @@
+-- jstat -gccapacity的容量列和无法识别的列
+ALTER TABLE MEMORY_INFO ADD COLUMN NGCMN REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN NGCMX REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN NGC REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN OGCMN REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN OGCMX REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN OGC REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN MCMN REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN MCMX REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN CCSMN REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN CCSMX REAL DEFAULT 0;
+ALTER TABLE MEMORY_INFO ADD COLUMN EXTRAS TEXT;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+UPDATE PROCESS_INFO SET EXE_TIME = SUBSTR(EXE_TIME, 12) WHERE EXE_TIME LIKE '%-%';
+UPDATE CPU_INFO SET EXE_TIME = SUBSTR(EXE_TIME, 12) WHERE EXE_TIME LIKE '%-%';
@@ -0,0 +1,8 @@
+-- CPU和进程的采集时间由时分秒改为完整的日期时间，历史进程数据的日期取自对应的采集文件
+UPDATE PROCESS_INFO
+SET EXE_TIME = (SELECT DATE(F.EXE_TIME) FROM FILE_INFO F WHERE F.ID = PROCESS_INFO.FILE_ID) || ' ' || EXE_TIME
+WHERE EXE_TIME NOT LIKE '%-%'
+  AND EXISTS (SELECT 1 FROM FILE_INFO F WHERE F.ID = PROCESS_INFO.FILE_ID AND F.EXE_TIME IS NOT NULL);
+
+-- 历史CPU数据没有关联采集文件，无法确定日期，保持原样且不参与查询。
+-- 这些工作空间的FILE_INFO没有文件大小和修改时间，重新扫描时会整体重新解析
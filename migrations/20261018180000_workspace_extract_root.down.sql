@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE FILE_WORKSPACE DROP COLUMN EXTRACT_ROOT;
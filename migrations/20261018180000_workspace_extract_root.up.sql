@@ -0,0 +1,2 @@
+-- 压缩包解压到的工作空间目录，直接打开文件夹时为空
+ALTER TABLE FILE_WORKSPACE ADD COLUMN EXTRACT_ROOT TEXT;
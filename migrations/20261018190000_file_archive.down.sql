@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE FILE_INFO DROP COLUMN LINE_INDEX;
+ALTER TABLE FILE_INFO DROP COLUMN ARCHIVE;
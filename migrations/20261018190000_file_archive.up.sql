@@ -0,0 +1,3 @@
+-- 压缩包中的文件直接从压缩包读取，ARCHIVE为压缩包路径，LINE_INDEX为行号到字节偏移的索引(JSON)
+ALTER TABLE FILE_INFO ADD COLUMN ARCHIVE TEXT;
+ALTER TABLE FILE_INFO ADD COLUMN LINE_INDEX TEXT;
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE CPU_INFO DROP COLUMN FILE_ID;
+ALTER TABLE FILE_INFO DROP COLUMN MODIFIED;
+ALTER TABLE FILE_INFO DROP COLUMN FILE_SIZE;
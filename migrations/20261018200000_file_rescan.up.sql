@@ -0,0 +1,5 @@
+-- 重新扫描文件夹时根据文件大小和修改时间（毫秒时间戳）判断文件是否变化
+ALTER TABLE FILE_INFO ADD COLUMN FILE_SIZE INTEGER;
+ALTER TABLE FILE_INFO ADD COLUMN MODIFIED INTEGER;
+-- 文件变化或删除时按文件删除CPU信息
+ALTER TABLE CPU_INFO ADD COLUMN FILE_ID TEXT;
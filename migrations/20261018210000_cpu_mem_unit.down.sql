@@ -0,0 +1,2 @@
+-- Add down migration script here
+ALTER TABLE CPU_INFO DROP COLUMN MEM_UNIT;
@@ -0,0 +1,5 @@
+-- 内存和交换区字段的单位，统一换算为KiB之前写入的数据保存的是top表头中的原始单位（KiB或MiB），单位未知时为NULL
+ALTER TABLE CPU_INFO ADD COLUMN MEM_UNIT TEXT;
+-- 负载、缓存和交换区字段与单位换算同时加入，历史数据中这些字段都是默认值0
+UPDATE CPU_INFO SET MEM_UNIT = 'KiB'
+WHERE LOAD1 <> 0 OR LOAD5 <> 0 OR LOAD15 <> 0 OR BUFF_CACHE <> 0 OR SWAP_TOTAL <> 0;
@@ -1,8 +1,10 @@
-use chrono::{Duration};
-use common::error::AnalysisError;
+use chrono::{DateTime, Local, NaiveDateTime};
+use common::error::{AnalysisError, ParseWarning};
+use common::file_utils::{trim_line_end, ArchiveReaders};
 use common::model::file_info::{FileInfo, FileType};
-use domain::model::cpu::Cpu;
-use domain::model::memory::{self, MemoryValue};
+use common::model::line_index::LineIndex;
+use domain::model::cpu::{Cpu, CpuThread};
+use domain::model::memory::{self, MemoryPercent, MemoryValue};
 use domain::model::thread::Thread;
 use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 
@@ -10,7 +12,6 @@ use std::collections::HashMap;
 use std::io::{self, BufRead};
 
 use std::{fs};
-use std::{path::Path};
 
 pub trait ParseFile<T, U> {
     fn parse(path: &str, files: &Vec<U>) -> Result<T, AnalysisError>;
@@ -18,150 +19,263 @@ pub trait ParseFile<T, U> {
 
 pub struct ThreadParser;
 pub struct CpuParser;
+pub struct CpuThreadParser;
 pub struct MemoryParser;
 
-impl ParseFile<Vec<Cpu>, FileInfo> for CpuParser {
-    fn parse(_path: &str, files: &Vec<FileInfo>) -> Result<Vec<Cpu>, AnalysisError> {
-        files
-            .iter()
-            .filter(|f| f.file_type == FileType::CpuTop)
-            .map(|file_info| {
-                let file = fs::File::open(&file_info.path)
-                    .map_err(|err| AnalysisError::IoError(err.to_string()))?;
-                let reader = io::BufReader::new(file);
-                let lines_storage: Vec<String> = reader
-                    .lines()
-                    .take(4) // 仅读取前三行
-                    .map(|line| line.map_err(|err| AnalysisError::ParseError(err.to_string())))
-                    .collect::<Result<Vec<String>, _>>()?;
-                Ok(Cpu::new(
-                    lines_storage,
-                    &file_info.id,
-                    &file_info.work_space,
-                ))
-            })
-            .collect::<Result<Vec<Cpu>, AnalysisError>>()
+/// 容错解析的结果，无法解析的文件会被跳过并记录在warnings中
+#[derive(Debug, Default)]
+pub struct Parsed<T> {
+    pub values: T,
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// 按行读取文件，读取失败时返回出错的行号
+fn read_lines(readers: &ArchiveReaders, file_info: &FileInfo) -> Result<Vec<String>, ParseWarning> {
+    let path = file_info.path.as_str();
+    let mut lines = Vec::new();
+    let mut failed_line = None;
+    readers.with_reader(path, file_info.archive.as_deref(), |reader| {
+        for line in reader.lines() {
+            failed_line = Some(lines.len() + 1);
+            lines.push(line?);
+        }
+        Ok(())
+    })
+    .map_err(|err| ParseWarning::new(path, failed_line, err.to_string()))?;
+    Ok(lines)
+}
+
+impl ParseFile<Parsed<Vec<Cpu>>, FileInfo> for CpuParser {
+    fn parse(_path: &str, files: &Vec<FileInfo>) -> Result<Parsed<Vec<Cpu>>, AnalysisError> {
+        let mut parsed = Parsed::<Vec<Cpu>>::default();
+        let readers = ArchiveReaders::default();
+        for file_info in files.iter().filter(|f| f.file_type == FileType::CpuTop) {
+            // 目录名中没有采集时间时，使用文件（压缩包中的文件使用压缩包）的修改时间补全日期
+            let file_time = file_info.time.or_else(|| {
+                fs::metadata(file_info.archive.as_ref().unwrap_or(&file_info.path))
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(|modified| DateTime::<Local>::from(modified).naive_local())
+            });
+            let cpu = read_lines(&readers, file_info).and_then(|lines| {
+                Cpu::new(lines, &file_info.id, &file_info.work_space, file_time)
+                    .map_err(|err| ParseWarning::from_line(&file_info.path, err))
+            });
+            match cpu {
+                Ok(cpu) => parsed.values.push(cpu),
+                Err(warning) => parsed.warnings.push(warning),
+            }
+        }
+        Ok(parsed)
     }
 }
 
-impl ParseFile<Vec<MemoryValue>, FileInfo> for MemoryParser {
-    fn parse(_path: &str, files: &Vec<FileInfo>) -> Result<Vec<MemoryValue>, AnalysisError> {
+impl ParseFile<Parsed<Vec<CpuThread>>, FileInfo> for CpuThreadParser {
+    fn parse(_path: &str, files: &Vec<FileInfo>) -> Result<Parsed<Vec<CpuThread>>, AnalysisError> {
+        let mut parsed = Parsed::<Vec<CpuThread>>::default();
+        let readers = ArchiveReaders::default();
+        for file_info in files.iter().filter(|f| f.file_type == FileType::CpuThread) {
+            let threads = read_lines(&readers, file_info).and_then(|lines| {
+                let threads = CpuThread::parse(&lines, &file_info.id, &file_info.work_space);
+                if threads.is_empty() {
+                    return Err(ParseWarning::new(&file_info.path, None, "未找到线程表"));
+                }
+                Ok(threads)
+            });
+            match threads {
+                Ok(threads) => parsed.values.extend(threads),
+                Err(warning) => parsed.warnings.push(warning),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// 解析jstat输出，返回-gc的容量信息和-gcutil的使用率信息，读取失败的文件作为警告跳过
+impl ParseFile<Parsed<(Vec<MemoryValue>, Vec<MemoryPercent>)>, FileInfo> for MemoryParser {
+    fn parse(_path: &str, files: &Vec<FileInfo>) -> Result<Parsed<(Vec<MemoryValue>, Vec<MemoryPercent>)>, AnalysisError> {
         let gc_file: Vec<FileInfo> = files
             .iter()
             .filter(|f| f.file_type == FileType::Gc)
             .cloned()
             .collect();
-        // 处理两种情况：
-        // 提取排序后的前两个元素
-        let mut cycle: Option<i64> = None;
-        let mut start_time = None;
-        let mut sorted_files: Vec<&FileInfo> =
-            gc_file.iter().filter(|f| f.time.is_some()).collect();
-        if sorted_files.len() > 1 && start_time.is_none() && cycle.is_none() {
-            sorted_files.sort_by_key(|f| f.time.unwrap());
-            if let (Some(first), Some(second)) = (sorted_files.get(0), sorted_files.get(1)) {
-                let first_time = first.time.unwrap();
-                let second_time = second.time.unwrap();
-                // 计算时间差值
-                let time_difference: Duration = second_time - first_time;
-                cycle = Some(time_difference.num_seconds());
-                start_time = Some(first_time);
-            }
-        }
-        let mut memory_infos = Vec::new();
+        let (start_time, cycle) = sample_cycle(&gc_file);
+        let mut parsed = Parsed::<(Vec<MemoryValue>, Vec<MemoryPercent>)>::default();
+        let (memory_infos, memory_percents) = &mut parsed.values;
+        let readers = ArchiveReaders::default();
         for file in gc_file.iter() {
-            let path = Path::new(&file.path);
-            let parent = path.parent().unwrap();
-            if parent.to_str().unwrap().contains("gc") {
-                memory_infos = memory::batch_crate_memory_info(
-                    file.path.as_str(),
-                    start_time,
-                    cycle,
-                );
+            let archive = file.archive.as_deref();
+            let result = if memory::is_periodic_gc(&file.path) {
+                readers.with_reader(&file.path, archive, |reader| {
+                    memory::batch_crate_memory_info(reader, &file.id, start_time, cycle)
+                })
+                .map(|memory_info| (memory_info, vec![]))
             } else {
-                let memory_info = memory::create(&file.path);
-                memory_infos.push(memory_info.0);
+                readers.with_reader(&file.path, archive, |reader| {
+                    memory::create(&file.path, &file.id, reader)
+                })
+                .map(|(memory_info, memory_percent)| (memory_info.into_iter().collect(), memory_percent.into_iter().collect()))
+            };
+            match result {
+                Ok((memory_info, memory_percent)) => {
+                    memory_infos.extend(memory_info);
+                    memory_percents.extend(memory_percent);
+                }
+                Err(err) => parsed.warnings.push(ParseWarning::new(&file.path, None, err.to_string())),
+            }
+        }
+        let gcutil_file: Vec<FileInfo> = files
+            .iter()
+            .filter(|f| f.file_type == FileType::GcUtil)
+            .cloned()
+            .collect();
+        // 与-gc一样，只有gc目录下定时采集的文件按文件之间的间隔推算采集周期
+        let periodic_file: Vec<FileInfo> = gcutil_file.iter().filter(|f| memory::is_periodic_gc(&f.path)).cloned().collect();
+        let (_, gcutil_cycle) = sample_cycle(&periodic_file);
+        for file in gcutil_file.iter() {
+            let cycle = if memory::is_periodic_gc(&file.path) { gcutil_cycle } else { None };
+            let result = readers.with_reader(&file.path, file.archive.as_deref(), |reader| {
+                memory::batch_crate_memory_percent(reader, &file.id, file.time, cycle)
+            });
+            match result {
+                Ok(memory_percent) => {
+                    // 没有-t时间戳列且无法推算采集周期时，只有第一行能确定采集时间
+                    let (timed, untimed): (Vec<MemoryPercent>, Vec<MemoryPercent>) =
+                        memory_percent.into_iter().partition(|percent| percent.time.is_some());
+                    if !untimed.is_empty() {
+                        parsed.warnings.push(ParseWarning::new(
+                            &file.path,
+                            None,
+                            format!("无法确定采集时间，跳过{}行数据", untimed.len()),
+                        ));
+                    }
+                    memory_percents.extend(timed);
+                }
+                Err(err) => parsed.warnings.push(ParseWarning::new(&file.path, None, err.to_string())),
             }
         }
-        Ok(memory_infos)
+        Ok(parsed)
     }
 }
 
-impl ParseFile<HashMap<String, Vec<Thread>>, FileInfo> for ThreadParser {
+/// 按采集时间排序后，以最早的两个文件的时间差作为定时采集的周期，返回最早的采集时间和周期（秒）
+fn sample_cycle(files: &[FileInfo]) -> (Option<NaiveDateTime>, Option<i64>) {
+    let mut times: Vec<NaiveDateTime> = files.iter().filter_map(|f| f.time).collect();
+    times.sort();
+    match (times.first(), times.get(1)) {
+        (Some(first), Some(second)) => (Some(*first), Some((*second - *first).num_seconds())),
+        _ => (None, None),
+    }
+}
+
+/// 解析堆栈文件，返回每个文件中的线程和文件的行索引，key为文件ID，读取失败的文件和无法解析的线程作为警告跳过
+impl ParseFile<Parsed<(HashMap<String, Vec<Thread>>, HashMap<String, LineIndex>)>, FileInfo> for ThreadParser {
     fn parse(
         _path: &str,
         files: &Vec<FileInfo>,
-    ) -> Result<HashMap<String, Vec<Thread>>, AnalysisError> {
+    ) -> Result<Parsed<(HashMap<String, Vec<Thread>>, HashMap<String, LineIndex>)>, AnalysisError> {
         let stack_file: Vec<FileInfo> = files
             .iter()
             .filter(|f| f.file_type == FileType::StackTrace)
             .cloned()
             .collect();
-        let thread_map : HashMap<String, Vec<Thread>>= stack_file
-        .par_iter()
-        .filter_map(|file_info| {
-            let path = Path::new(&file_info.path);
-            let file = fs::File::open(path).unwrap();
-
-            let reader = io::BufReader::new(file);
-            let mut thread_groups: Vec<Vec<String>> = Vec::new();
-            let mut current_thread: Vec<String> = Vec::new();
-            let mut start = false;
-            let mut line_number:i64 = 0;
-            let mut line_tag:Vec<(i64, i64)> = Vec::new();
-            for line in reader.lines() {
-                line_number+=1;
-                match line {
-                    Ok(line) => {
-                        if line.is_empty() {
-                            start = false;
-                            continue;
-                        }
-                        if line.contains("nid=") {
-                            start = true;
-                            if let Some(last) = line_tag.last_mut(){
-                                last.1 = line_number - 2;
-                            }
-                            line_tag.push((line_number, line_number));
-                            if !current_thread.is_empty() {
-                                thread_groups.push(current_thread);
-                                current_thread = Vec::new();
-                            }
-                        }
-                        if start {
-                            current_thread.push(line);
-                        }
+        let readers = ArchiveReaders::default();
+        let results: Vec<_> = stack_file
+            .par_iter()
+            .map(|file_info| (file_info, Self::read_threads(&readers, &file_info.path, file_info.archive.as_deref())))
+            .collect();
+        let mut parsed = Parsed::<(HashMap<String, Vec<Thread>>, HashMap<String, LineIndex>)>::default();
+        let (thread_map, indexes) = &mut parsed.values;
+        for (file_info, result) in results {
+            match result {
+                Ok(file_parsed) => {
+                    parsed.warnings.extend(file_parsed.warnings);
+                    let (threads, index) = file_parsed.values;
+                    if threads.is_empty() {
+                        parsed.warnings.push(ParseWarning::new(&file_info.path, None, "未找到线程"));
+                        continue;
                     }
-                    Err(_) => {}
+                    indexes.insert(file_info.id.clone(), index);
+                    thread_map.insert(file_info.id.clone(), threads);
                 }
+                Err(err) => parsed.warnings.push(ParseWarning::new(&file_info.path, None, err.to_string())),
             }
-            if !current_thread.is_empty() {
-                if let Some(last) = line_tag.last_mut() {
+        }
+        Ok(parsed)
+    }
+}
+
+impl ThreadParser {
+    /// 读取单个堆栈文件中的全部线程，记录每个线程在文件中的起止行，并建立行号到字节偏移的索引，无法解析的线程作为警告跳过
+    pub fn read_threads(readers: &ArchiveReaders, path: &str, archive: Option<&str>) -> Result<Parsed<(Vec<Thread>, LineIndex)>, AnalysisError> {
+        let (thread_groups, line_tag, index) = readers.with_reader(path, archive, Self::read_groups)
+            .map_err(|err| AnalysisError::IoError(format!("读取文件{}失败:{}", path, err)))?;
+        let results: Vec<Result<Thread, ParseWarning>> = thread_groups
+            .par_iter()
+            .enumerate()
+            .map(|(idx, group)| {
+                let (start, end) = line_tag[idx];
+                Thread::new(group, start, end)
+                    .map_err(|err| ParseWarning::new(path, usize::try_from(start).ok(), format!("解析线程失败:{}", err)))
+            })
+            .collect();
+        let mut parsed = Parsed::<(Vec<Thread>, LineIndex)>::default();
+        for result in results {
+            match result {
+                Ok(thread) => parsed.values.0.push(thread),
+                Err(warning) => parsed.warnings.push(warning),
+            }
+        }
+        parsed.values.1 = index;
+        Ok(parsed)
+    }
+
+    /// 按空行和`nid=`将文件内容切分为线程，返回线程的内容、起止行和行索引
+    #[allow(clippy::type_complexity)]
+    fn read_groups(reader: &mut dyn BufRead) -> io::Result<(Vec<Vec<String>>, Vec<(i64, i64)>, LineIndex)> {
+        let mut thread_groups: Vec<Vec<String>> = Vec::new();
+        let mut current_thread: Vec<String> = Vec::new();
+        let mut start = false;
+        let mut line_number:i64 = 0;
+        let mut line_tag:Vec<(i64, i64)> = Vec::new();
+        let mut index = LineIndex::default();
+        let mut offset: u64 = 0;
+        let mut buffer = Vec::new();
+        loop {
+            buffer.clear();
+            let read = reader.read_until(b'\n', &mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            line_number+=1;
+            index.record(line_number as usize, offset);
+            offset += read as u64;
+            let line = String::from_utf8_lossy(trim_line_end(&buffer)).into_owned();
+            if line.is_empty() {
+                start = false;
+                continue;
+            }
+            if line.contains("nid=") {
+                start = true;
+                if let Some(last) = line_tag.last_mut(){
                     last.1 = line_number - 2;
                 }
-                thread_groups.push(current_thread);
+                line_tag.push((line_number, line_number));
+                if !current_thread.is_empty() {
+                    thread_groups.push(current_thread);
+                    current_thread = Vec::new();
+                }
             }
-            let file_thread_info: Vec<Thread> = thread_groups
-                .par_iter()
-                .enumerate()
-                .filter_map(|(idx, group)| {
-                    match Thread::new(group,line_tag[idx].0, line_tag[idx].1) {
-                        Ok(thread) => Some(thread),
-                        Err(err) => {
-                            eprintln!("解析线程失败: {:?}", err); // 打印或记录错误
-                            None // 失败时返回 None
-                        }
-                    }
-                    
-                })
-                .collect();
-            if !file_thread_info.is_empty() {
-                Some((file_info.id.clone(), file_thread_info))   
-            }else {
-                None
+            if start {
+                current_thread.push(line);
             }
-        })
-        .collect();
-        Ok(thread_map)
+        }
+        if !current_thread.is_empty() {
+            if let Some(last) = line_tag.last_mut() {
+                last.1 = line_number - 2;
+            }
+            thread_groups.push(current_thread);
+        }
+        Ok((thread_groups, line_tag, index))
     }
 }
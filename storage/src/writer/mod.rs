@@ -2,15 +2,17 @@
 use std::collections::HashMap;
 
 use common::{error::AnalysisError};
-use domain::{db::{db::ModelTransfer, db_cpu::{self, DBCpu}, db_memory::{self, DBMemory}, db_thread::{self, DBThreadInfo}}, model::{cpu::Cpu, memory::MemoryValue, thread::Thread}};
+use domain::{db::{db::ModelTransfer, db_cpu::{self, DBCpu}, db_cpu_thread::{self, DBCpuThread}, db_lock::{self, DBLockInfo}, db_memory::{self, DBMemory}, db_memory_percent::{self, DBMemoryPercent}, db_process::{self, DBProcess}, db_thread::{self, DBThreadInfo}}, model::{cpu::{Cpu, CpuThread}, memory::{MemoryPercent, MemoryValue}, thread::Thread}};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use sqlx::SqlitePool;
+use sqlx::SqliteConnection;
 
 
 pub trait Writer{
-   async fn write_threads(pool: &SqlitePool, workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(),AnalysisError>;
-   async fn write_cpu(pool: &SqlitePool, workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError>;
-   async fn write_memory(pool: &SqlitePool, workspace_id: &str, memories: &Vec<MemoryValue>) -> Result<(),AnalysisError>;
+   async fn write_threads(conn: &mut SqliteConnection, workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(),AnalysisError>;
+   async fn write_cpu(conn: &mut SqliteConnection, workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError>;
+   async fn write_memory(conn: &mut SqliteConnection, workspace_id: &str, memories: &Vec<MemoryValue>) -> Result<(),AnalysisError>;
+   async fn write_cpu_threads(conn: &mut SqliteConnection, workspace_id: &str, cpu_threads: &[CpuThread]) -> Result<(), AnalysisError>;
+   async fn write_memory_percent(conn: &mut SqliteConnection, workspace_id: &str, percents: &[MemoryPercent]) -> Result<(), AnalysisError>;
 }
 
 
@@ -18,54 +20,75 @@ pub struct LocalWriter;
 struct DBWriter;
 
 impl Writer for LocalWriter{
-    async fn write_threads(pool: &SqlitePool, workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(),AnalysisError> {
-        DBWriter::write_threads(pool, workspace_id, threads_map).await?;
+    async fn write_threads(conn: &mut SqliteConnection, workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(),AnalysisError> {
+        DBWriter::write_threads(conn, workspace_id, threads_map).await?;
         Ok(())
       
     }
 
-    async fn write_cpu(pool: &SqlitePool, workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError> {
-        DBWriter::write_cpu(pool, workspace_id, cpus).await?;
+    async fn write_cpu(conn: &mut SqliteConnection, workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError> {
+        DBWriter::write_cpu(conn, workspace_id, cpus).await?;
         Ok(())
     }
 
-    async fn write_memory(pool: &SqlitePool, workspace_id: &str, memories: &Vec<MemoryValue>) -> Result<(),AnalysisError> {
-        DBWriter::write_memory(pool, workspace_id, memories).await?;
+    async fn write_memory(conn: &mut SqliteConnection, workspace_id: &str, memories: &Vec<MemoryValue>) -> Result<(),AnalysisError> {
+        DBWriter::write_memory(conn, workspace_id, memories).await?;
+        Ok(())
+    }
+
+    async fn write_cpu_threads(conn: &mut SqliteConnection, workspace_id: &str, cpu_threads: &[CpuThread]) -> Result<(), AnalysisError> {
+        DBWriter::write_cpu_threads(conn, workspace_id, cpu_threads).await?;
+        Ok(())
+    }
+
+    async fn write_memory_percent(conn: &mut SqliteConnection, workspace_id: &str, percents: &[MemoryPercent]) -> Result<(), AnalysisError> {
+        DBWriter::write_memory_percent(conn, workspace_id, percents).await?;
         Ok(())
     }
 } 
 
 impl Writer for DBWriter {
 
-    async fn write_threads(pool: &SqlitePool, _workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(), AnalysisError> {
-      let db_threads = threads_map
+    async fn write_threads(conn: &mut SqliteConnection, _workspace_id: &str, threads_map: &HashMap<String, Vec<Thread>>) -> Result<(), AnalysisError> {
+      let (db_threads, db_locks): (Vec<DBThreadInfo>, Vec<Vec<DBLockInfo>>) = threads_map
         .into_par_iter()
         .flat_map(|(key, value)| {
             value.into_par_iter().map(move |thread| {
-                DBThreadInfo::new(&thread, &key)
+                let db_thread = DBThreadInfo::new(thread, key);
+                let db_locks = DBLockInfo::from_thread(thread, &db_thread.id, key);
+                (db_thread, db_locks)
             })
         })
-        .collect::<Vec<DBThreadInfo>>();
-        db_thread::batch_add(pool, db_threads).await?;
+        .unzip();
+        db_thread::batch_add(conn, db_threads).await?;
+        db_lock::batch_add(conn, db_locks.into_iter().flatten().collect()).await?;
       Ok(())
     }
 
-    async fn write_cpu(pool: &SqlitePool,workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError> {
+    async fn write_cpu(conn: &mut SqliteConnection,workspace_id: &str, cpus: &Vec<Cpu>) -> Result<(), AnalysisError> {
            db_cpu::batch_add(
-        pool,
+        conn,
         cpus
             .into_iter()
             .map(|info| DBCpu::new(&info, &info.file_id, &workspace_id))
             .collect()
       )   
       .await
+        .map_err(|e| AnalysisError::DBError(e.to_string()))?;
+        db_process::batch_add(
+            conn,
+            cpus.iter()
+                .flat_map(|info| DBProcess::from_cpu(info, workspace_id))
+                .collect(),
+        )
+        .await
         .map_err(|e| AnalysisError::DBError(e.to_string()))?;
         Ok(())
     }
 
-    async fn write_memory(pool: &SqlitePool,workspace_id: &str, memory_info: &Vec<MemoryValue>) -> Result<(), AnalysisError> {
+    async fn write_memory(conn: &mut SqliteConnection,workspace_id: &str, memory_info: &Vec<MemoryValue>) -> Result<(), AnalysisError> {
         db_memory::batch_add(
-        pool,
+        conn,
         &memory_info
             .into_iter()
             .map(|mem| DBMemory::new(&mem,workspace_id))
@@ -75,5 +98,31 @@ impl Writer for DBWriter {
       .map_err(|e| AnalysisError::DBError(e.to_string()))?;
         Ok(())
     }
+
+    async fn write_cpu_threads(conn: &mut SqliteConnection, workspace_id: &str, cpu_threads: &[CpuThread]) -> Result<(), AnalysisError> {
+        db_cpu_thread::batch_add(
+            conn,
+            cpu_threads
+                .iter()
+                .map(|thread| DBCpuThread::new(thread, workspace_id))
+                .collect(),
+        )
+        .await
+        .map_err(|e| AnalysisError::DBError(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn write_memory_percent(conn: &mut SqliteConnection, workspace_id: &str, percents: &[MemoryPercent]) -> Result<(), AnalysisError> {
+        db_memory_percent::batch_add(
+            conn,
+            percents
+                .iter()
+                .map(|percent| DBMemoryPercent::new(percent, workspace_id))
+                .collect(),
+        )
+        .await
+        .map_err(|e| AnalysisError::DBError(e.to_string()))?;
+        Ok(())
+    }
     
 }
\ No newline at end of file
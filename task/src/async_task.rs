@@ -1,14 +1,25 @@
-use domain::context::Context;
+use common::error::ParseWarning;
+use domain::{config::AppConfig, context::Context};
 use serde::Serialize;
 use sqlx::SqlitePool;
 use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
+
+/// 每个任务保留的最大警告数，长期运行的任务只保留最新的警告
+const MAX_WARNINGS: usize = 1000;
+
+/// 进度、消息、阶段、结果和警告的增量更新，None表示不修改
+pub type TaskUpdate = (Option<f64>, Option<String>, Option<TaskPhase>, Option<String>, Option<Vec<ParseWarning>>);
 
 #[derive(Clone)]
 pub struct ExecuteContext {
     pub pool: Option<SqlitePool>,
-    pub channel: mpsc::Sender<(Option<f64>, Option<String>, Option<TaskPhase>, Option<String>)>,
-    pub param: Option<String>
+    pub channel: mpsc::Sender<TaskUpdate>,
+    pub param: Option<String>,
+    /// 提交任务时的配置快照，任务执行过程中不受配置修改的影响
+    pub config: Option<AppConfig>,
+    /// 任务被取消时变为true，长期运行的任务需要自行检查
+    pub cancel: watch::Receiver<bool>,
 }
 
 impl ExecuteContext {
@@ -18,12 +29,12 @@ impl ExecuteContext {
         if !(0.0..=100.0).contains(&value) {
             log::error!("非法进度之:{}", value);
             let clamped = value.clamp(0.0, 100.0);
-            let _ = self.channel.send((Some(clamped), message, Some(TaskPhase::Running), None)).await;
+            let _ = self.channel.send((Some(clamped), message, Some(TaskPhase::Running), None, None)).await;
             return;
         }
         log::info!("更新进度,progress:{}, value:{:?}", value, message);
         // 发送进度更新
-        let _ = self.channel.send((Some(value), message, None, None)).await;
+        let _ = self.channel.send((Some(value), message, None, None, None)).await;
         
     }
 
@@ -37,24 +48,54 @@ impl ExecuteContext {
             return Err(format!("非法进度值: {}", value));
         }
         self.channel
-            .send((Some(value), message, Some(TaskPhase::Running), None))
+            .send((Some(value), message, Some(TaskPhase::Running), None, None))
             .await
             .map_err(|e| format!("进度更新失败: {}", e))
     }
 
     pub async fn fail(&self, message: Option<String>) {
         let _ = self.channel
-            .send((None, message, Some(TaskPhase::Failed), None))
+            .send((None, message, Some(TaskPhase::Failed), None, None))
             .await
             .map_err(|e| format!("设置失败状态错误:{}", e));
     }
 
     pub async fn complate(&self, message: Option<String>, result: Option<String>){
       let _ = self.channel
-          .send((Some(100.0), message, Some(TaskPhase::Completed), result))
+          .send((Some(100.0), message, Some(TaskPhase::Completed), result, None))
           .await
           .map_err(|e| format!("设置完成状态错误:{}", e));
   }
+
+    /// 更新任务的阶段性结果，用于长期运行、不会自行结束的任务
+    pub async fn publish(&self, result: String) {
+        let _ = self.channel
+            .send((None, None, None, Some(result), None))
+            .await
+            .map_err(|e| format!("更新结果错误:{}", e));
+    }
+
+    /// 任务是否已被取消
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow()
+    }
+
+    /// 等待任务被取消，任务从执行器中移除时同样视为取消
+    pub async fn cancelled(&self) {
+        let mut cancel = self.cancel.clone();
+        let _ = cancel.wait_for(|cancelled| *cancelled).await;
+    }
+
+    /// 追加执行过程中跳过的文件，任务不会因此失败，超过`MAX_WARNINGS`时丢弃最早的警告
+    pub async fn warn(&self, warnings: Vec<ParseWarning>) {
+        if warnings.is_empty() {
+            return;
+        }
+        let _ = self.channel
+            .send((None, None, None, None, Some(warnings)))
+            .await
+            .map_err(|e| format!("添加警告错误:{}", e));
+    }
 }
 
 #[derive(Clone, PartialEq, Default, Serialize)]
@@ -70,11 +111,13 @@ pub struct TaskStatus {
     pub progress: f64,
     pub message: Option<String>,
     pub phase: TaskPhase,
-    pub result: Option<String>
+    pub result: Option<String>,
+    pub warnings: Vec<ParseWarning>,
 }
 
 pub struct TaskHandle {
     pub status: Arc<Mutex<TaskStatus>>,
+    pub cancel: watch::Sender<bool>,
 }
 
 #[async_trait::async_trait]
@@ -101,13 +144,12 @@ impl TaskExecutor {
         param: Option<String>
     ) {
         // 创建进度更新通道
-        let (progress_tx, mut progress_rx): (
-            mpsc::Sender<(Option<f64>, Option<String>, Option<TaskPhase>, Option<String>)>,
-            mpsc::Receiver<(Option<f64>, Option<String>, Option<TaskPhase>, Option<String>)>,
-        ) = mpsc::channel(10);
+        let (progress_tx, mut progress_rx): (mpsc::Sender<TaskUpdate>, mpsc::Receiver<TaskUpdate>) =
+            mpsc::channel(10);
 
         // 创建共享状态
         let status = Arc::new(Mutex::new(TaskStatus::default()));
+        let (cancel_tx, cancel_rx) = watch::channel(false);
 
         let execute_id = task_id.to_string().clone();
         // Insert initial status
@@ -117,6 +159,7 @@ impl TaskExecutor {
                 execute_id.clone(),
                 TaskHandle {
                     status: status.clone(),
+                    cancel: cancel_tx,
                 },
             );
         }
@@ -125,7 +168,7 @@ impl TaskExecutor {
         let status_clone = status.clone();
 
         tokio::spawn(async move {
-            while let Some((progress, message, phase,result)) = progress_rx.recv().await {
+            while let Some((progress, message, phase, result, warnings)) = progress_rx.recv().await {
                 let mut status = status_clone.lock().await;
                 if let Some(progress) = progress {
                     status.progress = progress;
@@ -139,13 +182,20 @@ impl TaskExecutor {
                 if let Some(result) = result {
                     status.result = Some(result);
                 }
+                if let Some(warnings) = warnings {
+                    status.warnings.extend(warnings);
+                    let overflow = status.warnings.len().saturating_sub(MAX_WARNINGS);
+                    status.warnings.drain(..overflow);
+                }
             }
         });
         // 启动任务执行
         let execute_context = ExecuteContext {
             pool:Some(context.pool.clone()),
             channel: progress_tx,
-            param
+            param,
+            config: Some(context.shared_config.get()),
+            cancel: cancel_rx,
         };
         tokio::spawn(async move {
           match task.execute(&execute_context).await{
@@ -163,13 +213,25 @@ impl TaskExecutor {
                 progress: status.progress,
                 message: status.message.clone(),
                 phase: status.phase.clone(),
-                result: status.result.clone()
+                result: status.result.clone(),
+                warnings: status.warnings.clone(),
             })
         } else {
             None
         }
     }
 
+    /// 取消执行中的任务，任务不存在或已结束时返回false
+    pub async fn cancel_task(&self, task_id: &str) -> bool {
+        let tasks = self.tasks.lock().await;
+        match tasks.get(task_id) {
+            Some(handle) if handle.status.lock().await.phase == TaskPhase::Running => {
+                handle.cancel.send(true).is_ok()
+            }
+            _ => false,
+        }
+    }
+
     pub async fn remove_task(&self, task_id: &str) -> Option<TaskHandle> {
         let mut tasks = self.tasks.lock().await;
         tasks.remove(task_id)
@@ -36,6 +36,10 @@ async fn main() -> io::Result<()> {
     let cfg = shared_config.get();
     // 引入数据库
     let pool: sqlx::Pool<sqlx::Sqlite> = establish_connection().await;
+    // 旧版本导入的数据需要在处理请求之前升级
+    if let Err(err) = service::file_service::upgrade_legacy_work_spaces(&pool).await {
+        error!("升级旧版本工作空间时发生错误:{:?}", err);
+    }
     // 异步任务执行器
     let executor: TaskExecutor = TaskExecutor::new();
     let context = Context {
@@ -1,9 +1,9 @@
 
 use actix_web::{web, HttpResponse};
 use common::error::AnalysisError;
-use domain::db::db_cpu;
+use domain::{db::db_cpu, model::cpu::{HotThreadQuery, ProcessQuery}};
 
-use crate::{service::cpu_service, state::AppState};
+use crate::{resp::ApiResponse, service::cpu_service, state::AppState};
 
 #[allow(dead_code)]
 pub async fn query_cpu(
@@ -23,4 +23,26 @@ pub async fn cpu_used_count(
         Err(err) => Err(AnalysisError::DBError(format!("对象转换错误:{}", err))),
     }
    
-}
\ No newline at end of file
+}
+
+pub async fn count_process_cpu(
+    app_state: web::Data<AppState>,
+    workspace_id: web::Path<String>,
+    query: web::Query<ProcessQuery>,
+) -> Result<HttpResponse, AnalysisError> {
+    match cpu_service::count_process_cpu(&app_state.context.pool, &workspace_id, query.limit).await {
+        Ok(count) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(count)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
+
+pub async fn list_hot_threads(
+    app_state: web::Data<AppState>,
+    workspace_id: web::Path<String>,
+    query: web::Query<HotThreadQuery>,
+) -> Result<HttpResponse, AnalysisError> {
+    match cpu_service::list_hot_threads(&app_state.context.pool, &workspace_id, query.limit).await {
+        Ok(threads) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(threads)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
@@ -1,7 +1,8 @@
 use actix_web::{web, HttpResponse};
 use common::{error::AnalysisError, string_utils::rand_id};
+use task::async_task::TaskPhase;
 
-use crate::{ executor::{file_prase::ParseFileAsyncTask, stack_build::BuildCacheAsyncTask}, resp::ApiResponse, service::file_service, state::AppState};
+use crate::{ executor::{file_prase::ParseFileAsyncTask, rescan::RescanAsyncTask, stack_build::BuildCacheAsyncTask, watch::{watch_task_id, WatchAsyncTask}}, resp::ApiResponse, service::file_service, state::AppState};
 
 /// 将文件内容解析为工作空间，并存储到数据库中
 /// # Arguments
@@ -19,7 +20,7 @@ use crate::{ executor::{file_prase::ParseFileAsyncTask, stack_build::BuildCacheA
 /// ```
 /// # Note
 /// 此函数会加载指定文件，
-/// 如果在该位置工作空间已存在，则直接返回成功响应。
+/// 如果在该位置工作空间已存在，文件夹会提交重新扫描任务并返回任务ID，压缩包直接返回成功响应。
 /// 如果在该位置工作空间不存在，则会创建一个新的工作空间，并将文件内容解析为线程信息。
 /// # Panics
 /// 如果在解析文件或查询工作空间时发生错误，将触发 panic。
@@ -29,11 +30,17 @@ pub async fn load_file_handler(
     app_state: web::Data<AppState>,
     path: String
 ) -> Result<HttpResponse, AnalysisError>  {
-    match file_service::exist_work_space(&app_state.context.pool, &path).await {
-        Ok(exist) => {
-            match exist {
-                true => Ok(HttpResponse::Ok().json(ApiResponse::ok())),
-                false => {
+    match file_service::find_work_space(&app_state.context.pool, &path).await {
+        Ok(work_space) => {
+            match work_space {
+                // 文件夹中可能放入了新的dump文件，重新扫描只解析变化的部分
+                Some(work_space) if work_space.extract_root.is_none() && std::path::Path::new(&path).is_dir() => {
+                    let task_id = rand_id();
+                    app_state.executor.submit_task(&task_id, RescanAsyncTask, &app_state.context, Some(work_space.id)).await;
+                    Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id.to_string()))))
+                }
+                Some(_) => Ok(HttpResponse::Ok().json(ApiResponse::ok())),
+                None => {
                     let task_id = rand_id();
                     app_state.executor.submit_task(&task_id, ParseFileAsyncTask, &app_state.context, Some(path.to_string())).await;
                     Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id.to_string()))))
@@ -111,4 +118,53 @@ pub async fn load_file_workspace(
         let task_id = rand_id();
         app_state.executor.submit_task(&task_id, BuildCacheAsyncTask, &app_state.context, Some(work_space_id.clone())).await;
     Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id.to_string()))))
-}
\ No newline at end of file
+}
+
+/// 重新扫描文件夹工作空间，解析新增或修改的文件并删除已不存在的文件的数据
+/// # Arguments
+/// * `app_state` - 应用状态，包含数据库连接池和其他共享资源
+/// * `work_space_id` - 工作空间的唯一标识符
+/// # Returns
+/// * `Result<HttpResponse, AnalysisError>` - 返回任务ID，任务结果为新增、修改和删除的文件数
+pub async fn rescan_work_space(
+    app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>) -> Result<HttpResponse, AnalysisError> {
+    let task_id = rand_id();
+    app_state.executor.submit_task(&task_id, RescanAsyncTask, &app_state.context, Some(work_space_id.into_inner())).await;
+    Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id))))
+}
+
+/// 开始监听文件夹工作空间，定时导入新写入的文件
+/// # Arguments
+/// * `app_state` - 应用状态，包含数据库连接池和其他共享资源
+/// * `work_space_id` - 工作空间的唯一标识符
+/// # Returns
+/// * `Result<HttpResponse, AnalysisError>` - 返回监听任务ID，通过任务进度查询监听状态，
+///   工作空间已在监听中时返回已有的任务ID
+pub async fn start_watch(
+    app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>) -> Result<HttpResponse, AnalysisError> {
+    let task_id = watch_task_id(&work_space_id);
+    let running = app_state.executor.get_task_status(&task_id).await
+        .is_some_and(|status| status.phase == TaskPhase::Running);
+    if !running {
+        app_state.executor.submit_task(&task_id, WatchAsyncTask, &app_state.context, Some(work_space_id.into_inner())).await;
+    }
+    Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id))))
+}
+
+/// 停止监听文件夹工作空间
+/// # Arguments
+/// * `app_state` - 应用状态，包含数据库连接池和其他共享资源
+/// * `work_space_id` - 工作空间的唯一标识符
+/// # Returns
+/// * `Result<HttpResponse, AnalysisError>` - 返回监听任务ID，工作空间未在监听中时返回NotFound
+pub async fn stop_watch(
+    app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>) -> Result<HttpResponse, AnalysisError> {
+    let task_id = watch_task_id(&work_space_id);
+    match app_state.executor.cancel_task(&task_id).await {
+        true => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(task_id)))),
+        false => Err(AnalysisError::NotFound(format!("工作空间{}未在监听中", work_space_id))),
+    }
+}
@@ -2,13 +2,34 @@ use actix_web::{web, HttpResponse};
 use common::error::AnalysisError;
 use domain::db::db_memory;
 
-use crate::state::AppState;
+use crate::{resp::ApiResponse, service::memory_service, state::AppState};
 
-#[allow(dead_code)]
 pub async fn query_memeory(
     app_state: web::Data<AppState>,
-    path: web::Json<String>,
+    workspace_id: web::Path<String>,
 ) -> Result<HttpResponse, AnalysisError> {
-    let memrory = db_memory::list(&app_state.context.pool, &path).await?;
-    Ok(HttpResponse::Ok().json(memrory))
-}
\ No newline at end of file
+    match db_memory::list(&app_state.context.pool, &workspace_id).await {
+        Ok(memory) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(memory)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
+
+pub async fn count_memory_percent(
+    app_state: web::Data<AppState>,
+    workspace_id: web::Path<String>,
+) -> Result<HttpResponse, AnalysisError> {
+    match memory_service::count_memory_percent(&app_state.context.pool, &workspace_id).await {
+        Ok(count) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(count)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
+
+pub async fn count_gc_activity(
+    app_state: web::Data<AppState>,
+    workspace_id: web::Path<String>,
+) -> Result<HttpResponse, AnalysisError> {
+    match memory_service::count_gc_activity(&app_state.context.pool, &workspace_id).await {
+        Ok(activity) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(activity)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
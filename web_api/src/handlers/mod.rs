@@ -3,4 +3,6 @@ pub mod file;
 pub mod thread;
 pub mod memory;
 pub mod cpu;
-pub mod async_task;
\ No newline at end of file
+pub mod async_task;
+pub mod stack;
+pub mod timeline;
\ No newline at end of file
@@ -0,0 +1,39 @@
+use actix_web::{web, HttpResponse};
+use common::error::AnalysisError;
+use domain::model::stack::{CallTreeQuery, FlameQuery};
+
+use crate::{resp::ApiResponse, service::call_tree, state::AppState};
+
+pub async fn flame_graph(app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>,
+    query: web::Query<FlameQuery>,) -> Result<HttpResponse, AnalysisError> {
+        match call_tree::flame_graph(&app_state.context.pool, &work_space_id, &query).await {
+            Ok(flame) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(flame)))),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
+
+/// folded格式直接以纯文本返回，便于保存后交给火焰图工具
+pub async fn folded_stacks(app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>,
+    query: web::Query<FlameQuery>,) -> Result<HttpResponse, AnalysisError> {
+        match call_tree::folded_stacks(&app_state.context.pool, &work_space_id, &query).await {
+            Ok(folded) => Ok(HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(folded)),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
+
+pub async fn query_call_tree_node(app_state: web::Data<AppState>,
+    query: web::Json<CallTreeQuery>,) -> Result<HttpResponse, AnalysisError> {
+        match call_tree::get_node(&app_state.context.pool, &query.into()).await {
+            Ok(node) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(node)))),
+            Err(err @ AnalysisError::NotFound(_)) => Err(err),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
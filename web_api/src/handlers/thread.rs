@@ -1,6 +1,6 @@
 use actix_web::{web, HttpResponse};
 use common::error::AnalysisError;
-use domain::model::thread::{StatusQuery, ThreadsQuery};
+use domain::model::{thread::{StatusQuery, ThreadsQuery}, timeline::{StuckQuery, TimelineQuery}};
 
 use crate::{resp::ApiResponse, service::{file_service, thread_dump}, state::AppState};
 
@@ -69,3 +69,61 @@ pub async fn get_thread_content(app_state: web::Data<AppState>,
         }
 }
 
+
+
+pub async fn query_thread_timeline(app_state: web::Data<AppState>,
+    query: web::Json<TimelineQuery>) -> Result<HttpResponse, AnalysisError> {
+        match thread_dump::list_thread_timeline(&app_state.context.pool, &query).await {
+            Ok(timelines) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(timelines)))),
+            Err(err) => Err(AnalysisError::DBError(format!("对象转换错误:{}", err))),
+        }
+}
+
+/// 查询工作空间中卡住的线程，未指定阈值时使用配置中的analysis.stuck_threshold
+pub async fn list_stuck_threads(app_state: web::Data<AppState>,
+    work_space_id: web::Path<String>,
+    query: web::Query<StuckQuery>) -> Result<HttpResponse, AnalysisError> {
+        let threshold = query
+            .threshold
+            .unwrap_or_else(|| app_state.context.shared_config.get().analysis.stuck_threshold);
+        match thread_dump::find_stuck_threads(&app_state.context.pool, &work_space_id, threshold).await {
+            Ok(threads) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(threads)))),
+            Err(err @ AnalysisError::InvalidParam(_)) => Err(err),
+            Err(err) => Err(AnalysisError::DBError(format!("对象转换错误:{}", err))),
+        }
+}
+
+pub async fn diff_dumps(app_state: web::Data<AppState>,
+    file_ids: web::Path<(String, String)>,) -> Result<HttpResponse, AnalysisError> {
+        let (base_file_id, target_file_id) = file_ids.into_inner();
+        match thread_dump::diff_dumps(&app_state.context.pool, &base_file_id, &target_file_id).await {
+            Ok(diff) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(diff)))),
+            Err(err @ AnalysisError::InvalidParam(_)) => Err(err),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
+
+pub async fn list_deadlocks(app_state: web::Data<AppState>,
+    file_id: web::Path<String>,) -> Result<HttpResponse, AnalysisError> {
+        match thread_dump::detect_deadlocks(&app_state.context.pool, &file_id).await {
+            Ok(cycles) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(cycles)))),
+            Err(err @ AnalysisError::NotFound(_)) => Err(err),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
+
+
+pub async fn count_lock_contention(app_state: web::Data<AppState>,
+    file_id: web::Path<String>,) -> Result<HttpResponse, AnalysisError> {
+        match thread_dump::count_lock_contention(&app_state.context.pool, &file_id).await {
+            Ok(contentions) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(contentions)))),
+            Err(err @ AnalysisError::NotFound(_)) => Err(err),
+            Err(err) => {
+                    Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str())))
+            }
+        }
+}
@@ -0,0 +1,16 @@
+use actix_web::{web, HttpResponse};
+use common::error::AnalysisError;
+use domain::model::correlated::CorrelatedQuery;
+
+use crate::{resp::ApiResponse, service::timeline_service, state::AppState};
+
+pub async fn correlated_timeline(
+    app_state: web::Data<AppState>,
+    workspace_id: web::Path<String>,
+    query: web::Query<CorrelatedQuery>,
+) -> Result<HttpResponse, AnalysisError> {
+    match timeline_service::correlate(&app_state.context.pool, &workspace_id, query.interval).await {
+        Ok(timeline) => Ok(HttpResponse::Ok().json(ApiResponse::success(Some(timeline)))),
+        Err(err) => Ok(HttpResponse::Ok().json(ApiResponse::error(201, format!("执行失败:{}", err).as_str()))),
+    }
+}
@@ -1,7 +1,7 @@
 
 use actix_web::web;
 
-use crate::handlers::{async_task::query_task_process, cpu::cpu_used_count, file::{clean_open_file, list_work_space, load_file_handler, load_file_workspace}, general::health_check_handler, thread::{count_file_status, count_file_threads, count_thread_status, get_thread_content, list_dump_handler, query_threads}};
+use crate::handlers::{async_task::query_task_process, cpu::{count_process_cpu, cpu_used_count, list_hot_threads}, file::{clean_open_file, list_work_space, load_file_handler, load_file_workspace, rescan_work_space, start_watch, stop_watch}, general::health_check_handler, memory::{count_gc_activity, count_memory_percent, query_memeory}, stack::{flame_graph, folded_stacks, query_call_tree_node}, timeline::correlated_timeline, thread::{count_file_status, count_lock_contention, diff_dumps, count_file_threads, count_thread_status, get_thread_content, list_deadlocks, list_dump_handler, list_stuck_threads, query_thread_timeline, query_threads}};
 
 
 pub fn general_routers(cfg: &mut web::ServiceConfig) {
@@ -17,6 +17,9 @@ pub fn file_routes(cfg: &mut web::ServiceConfig) {
             .route("/open", web::post().to(load_file_handler))
             .route("/list", web::get().to(list_work_space))
             .route("/load/{work_space_id}", web::get().to(load_file_workspace))
+            .route("/rescan/{work_space_id}", web::post().to(rescan_work_space))
+            .route("/watch/start/{work_space_id}", web::post().to(start_watch))
+            .route("/watch/stop/{work_space_id}", web::post().to(stop_watch))
             .route("/clean", web::get().to(clean_open_file))
     )
     .service(
@@ -25,16 +28,39 @@ pub fn file_routes(cfg: &mut web::ServiceConfig) {
             .route("/count_file_status", web::post().to(count_file_status))
             .route("/count_thread_status", web::post().to(count_thread_status))
             .route("/list_thread_pool/{file_id}", web::get().to(count_file_threads))
+            .route("/deadlock/{file_id}", web::get().to(list_deadlocks))
+            .route("/lock_contention/{file_id}", web::get().to(count_lock_contention))
+            .route("/diff/{base_file_id}/{target_file_id}", web::get().to(diff_dumps))
     )
     .service(
         web::scope("/thread")
             .route("/query", web::post().to(query_threads))
             .route("/content/{thread_id}", web::get().to(get_thread_content))
+            .route("/timeline", web::post().to(query_thread_timeline))
+            .route("/stuck/{work_space_id}", web::get().to(list_stuck_threads))
+    )
+    .service(
+        web::scope("/stack")
+            .route("/flame/{work_space_id}", web::get().to(flame_graph))
+            .route("/folded/{work_space_id}", web::get().to(folded_stacks))
+            .route("/node", web::post().to(query_call_tree_node))
     )
     .service(
         web::scope("/cpu")
             .route("/count_info/{workspace_id}", web::get().to(cpu_used_count))
+            .route("/hot_threads/{workspace_id}", web::get().to(list_hot_threads))
+            .route("/process/{workspace_id}", web::get().to(count_process_cpu))
             
     )
+    .service(
+        web::scope("/memory")
+            .route("/list/{workspace_id}", web::get().to(query_memeory))
+            .route("/percent/{workspace_id}", web::get().to(count_memory_percent))
+            .route("/gc_activity/{workspace_id}", web::get().to(count_gc_activity))
+    )
+    .service(
+        web::scope("/timeline")
+            .route("/{workspace_id}", web::get().to(correlated_timeline))
+    )
     ;
 }
\ No newline at end of file
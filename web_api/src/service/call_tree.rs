@@ -0,0 +1,74 @@
+use std::{collections::HashMap, sync::Arc};
+
+use common::error::AnalysisError;
+use domain::{db::{db_file, db_thread::{self, DBThreadInfo}}, model::stack::{CallTree, CallTreeNode, CallTreeQuery, FlameNode, FlameQuery}};
+use indexer::cache::global::{CacheKey, GlobalCache};
+use sqlx::SqlitePool;
+
+/// 获取工作空间的调用树，优先读取缓存，按过滤条件构建的调用树缓存在容量有限的L1缓存中
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space_id` - 工作空间ID
+/// * `query` - 线程过滤条件
+/// # Returns
+/// * `Result<Arc<Vec<Arc<CallTree>>>, AnalysisError>` - 调用树的根节点集合
+pub async fn load_call_tree(pool: &SqlitePool, work_space_id: &str, query: &FlameQuery) -> Result<Arc<Vec<Arc<CallTree>>>, AnalysisError> {
+    if query.is_empty() {
+        let key = CacheKey::call_tree(work_space_id);
+        if let Some(call_tree) = GlobalCache::get::<Vec<Arc<CallTree>>>(&key) {
+            return Ok(call_tree);
+        }
+        let threads = db_thread::list_by_work_space(pool, work_space_id).await?;
+        let call_tree = build_call_tree(pool, work_space_id, threads).await?;
+        GlobalCache::put(key, call_tree.clone());
+        return Ok(Arc::new(call_tree));
+    }
+    let key = CacheKey::filtered_call_tree(work_space_id, &query.cache_key());
+    if let Some(call_tree) = GlobalCache::get::<Vec<Arc<CallTree>>>(&key) {
+        return Ok(call_tree);
+    }
+    let threads = db_thread::list_by_filter(pool, work_space_id, query).await?;
+    let call_tree = build_call_tree(pool, work_space_id, threads).await?;
+    GlobalCache::put_hot(key, call_tree.clone());
+    Ok(Arc::new(call_tree))
+}
+
+/// 使用线程所属dump的采集时间构建调用树
+pub async fn build_call_tree(pool: &SqlitePool, work_space_id: &str, threads: Vec<DBThreadInfo>) -> Result<Vec<Arc<CallTree>>, AnalysisError> {
+    let times: HashMap<String, _> = db_file::list(pool, work_space_id)
+        .await?
+        .into_iter()
+        .map(|file| (file.id, file.exe_time))
+        .collect();
+    let mut samples: Vec<_> = threads
+        .iter()
+        .map(|thread| (thread.to_thread(), times.get(&thread.file_id).copied().flatten()))
+        .collect();
+    // 节点上的采集时间按先后顺序排列
+    samples.sort_by_key(|(_, time)| *time);
+    Ok(CallTree::from_samples(samples))
+}
+
+/// 查询调用树中的单个节点及其直接子节点
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `query` - 节点ID或方法路径，以及线程过滤条件
+/// # Returns
+/// * `Result<CallTreeNode, AnalysisError>` - 节点的采样数、经过的线程和子节点摘要
+pub async fn get_node(pool: &SqlitePool, query: &CallTreeQuery) -> Result<CallTreeNode, AnalysisError> {
+    let call_tree = load_call_tree(pool, &query.work_space_id, &query.filter).await?;
+    CallTree::find_node(&call_tree, query.id.as_deref(), query.path.as_deref())
+        .ok_or_else(|| AnalysisError::NotFound("调用树节点不存在".to_string()))
+}
+
+/// 以d3-flamegraph的JSON格式导出调用树
+pub async fn flame_graph(pool: &SqlitePool, work_space_id: &str, query: &FlameQuery) -> Result<FlameNode, AnalysisError> {
+    let call_tree = load_call_tree(pool, work_space_id, query).await?;
+    Ok(CallTree::to_flame_graph(&call_tree))
+}
+
+/// 以folded格式导出调用树，可直接交给flamegraph.pl、speedscope等工具使用
+pub async fn folded_stacks(pool: &SqlitePool, work_space_id: &str, query: &FlameQuery) -> Result<String, AnalysisError> {
+    let call_tree = load_call_tree(pool, work_space_id, query).await?;
+    Ok(CallTree::to_folded(&call_tree))
+}
@@ -1,23 +1,134 @@
-use common::error::AnalysisError;
-use domain::{db::db_cpu, model::cpu::CpuCount};
+use std::collections::{HashMap, HashSet};
+
+use common::{error::AnalysisError, model::file_info::FileType};
+use domain::{db::{db_cpu, db_cpu_thread, db_file, db_process, db_thread::{self, DBThreadInfo}}, model::{cpu::{CpuCount, HotThread, ProcessCpuCount}, thread::{Frame, ThreadStatus}}};
 use sqlx::SqlitePool;
 
 
 /// 获取线程详情
 pub async fn count_cpu_status(pool: &SqlitePool, work_space: &str) -> Result<CpuCount, AnalysisError> {
-  match db_cpu::count_info(pool, &work_space).await{
+  match db_cpu::count_info(pool, work_space).await{
       Ok(cpu_info) => {
-          let exe_time = cpu_info.iter().map(|c| c.exe_time).collect();
-          let us = cpu_info.iter().map(|c| c.us).collect();
-          let sy = cpu_info.iter().map(|c| c.sy).collect();
-          let ids = cpu_info.iter().map(|c| c.ids).collect();
-          return Ok(CpuCount{
-            exe_time,
-            us,
-            sy,
-            ids,
-        });
+          Ok(CpuCount{
+            exe_time: cpu_info.iter().map(|c| c.exe_time).collect(),
+            load1: cpu_info.iter().map(|c| c.load1).collect(),
+            load5: cpu_info.iter().map(|c| c.load5).collect(),
+            load15: cpu_info.iter().map(|c| c.load15).collect(),
+            us: cpu_info.iter().map(|c| c.us).collect(),
+            sy: cpu_info.iter().map(|c| c.sy).collect(),
+            ni: cpu_info.iter().map(|c| c.ni).collect(),
+            ids: cpu_info.iter().map(|c| c.ids).collect(),
+            wa: cpu_info.iter().map(|c| c.wa).collect(),
+            hi: cpu_info.iter().map(|c| c.hi).collect(),
+            si: cpu_info.iter().map(|c| c.si).collect(),
+            st: cpu_info.iter().map(|c| c.st).collect(),
+            buff_cache: cpu_info.iter().map(|c| c.buff_cache).collect(),
+            swap_total: cpu_info.iter().map(|c| c.swap_total).collect(),
+            swap_free: cpu_info.iter().map(|c| c.swap_free).collect(),
+            swap_used: cpu_info.iter().map(|c| c.swap_used).collect(),
+            mem_unit: cpu_info.iter().map(|c| c.mem_unit.clone()).collect(),
+        })
       },
       Err(err) => Err(AnalysisError::DBError(format!("查询CPU状态错误:{}", err))),
   }
-}
\ No newline at end of file
+}
+
+/// 默认返回的进程数量
+const DEFAULT_PROCESS_LIMIT: usize = 10;
+
+/// 按进程统计各个采集时间点的CPU占用，用于区分是JVM还是其他进程占用了CPU
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space` - 工作空间ID
+/// * `limit` - 返回CPU峰值最高的进程数量，默认10
+/// # Returns
+/// * `Result<ProcessCpuCount, AnalysisError>` - 以采集时间为横轴的进程CPU序列
+pub async fn count_process_cpu(pool: &SqlitePool, work_space: &str, limit: Option<usize>) -> Result<ProcessCpuCount, AnalysisError> {
+    let processes = db_process::list_by_work_space(pool, work_space).await?;
+    let samples = processes
+        .iter()
+        .map(|process| (process.exe_time, process.to_process()))
+        .collect();
+    Ok(ProcessCpuCount::new(samples, limit.unwrap_or(DEFAULT_PROCESS_LIMIT)))
+}
+
+/// 默认返回的高CPU线程数量
+const DEFAULT_HOT_THREAD_LIMIT: usize = 20;
+
+/// 列出CPU占用最高的线程，并按nid关联到采集时间最接近的jstack中的Java线程
+///
+/// 同一进程的同一线程在多次top采集中会重复出现，按(pid, nid, 最近的dump)去重后只保留CPU最高的一条；
+/// 最近的dump中找不到对应nid的线程不是Java线程，直接跳过
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space` - 工作空间ID
+/// * `limit` - 返回的线程数量，默认20
+/// # Returns
+/// * `Result<Vec<HotThread>, AnalysisError>` - 按CPU占用率降序排列的线程
+pub async fn list_hot_threads(pool: &SqlitePool, work_space: &str, limit: Option<usize>) -> Result<Vec<HotThread>, AnalysisError> {
+    let cpu_threads = db_cpu_thread::list_by_work_space(pool, work_space).await?;
+    let files = db_file::list(pool, work_space).await?;
+    let file_times: HashMap<&str, _> = files.iter().map(|f| (f.id.as_str(), f.exe_time)).collect();
+    let dumps: Vec<(String, _)> = files
+        .iter()
+        .filter(|f| f.file_type == i8::from(FileType::StackTrace))
+        .map(|f| (f.id.clone(), f.exe_time))
+        .collect();
+    // 每个dump文件的线程按nid建立索引，只查询一次
+    let mut dump_threads: HashMap<String, HashMap<String, DBThreadInfo>> = HashMap::new();
+    let limit = limit.unwrap_or(DEFAULT_HOT_THREAD_LIMIT);
+    // 查询结果已按CPU降序排列，每个键第一次出现的就是CPU最高的一条
+    let mut seen = HashSet::new();
+    let mut hot_threads = Vec::new();
+    for cpu_thread in cpu_threads {
+        if hot_threads.len() >= limit {
+            break;
+        }
+        let time = file_times.get(cpu_thread.file_id.as_str()).copied().flatten();
+        let dump_file_id = HotThread::closest_dump(time, &dumps);
+        let thread = match &dump_file_id {
+            Some(dump_file_id) => {
+                if !dump_threads.contains_key(dump_file_id) {
+                    let threads = db_thread::list_threads(pool, dump_file_id, &None, &None).await?;
+                    dump_threads.insert(
+                        dump_file_id.clone(),
+                        threads.into_iter().map(|t| (t.nid.to_lowercase(), t)).collect(),
+                    );
+                }
+                dump_threads.get(dump_file_id).and_then(|threads| threads.get(&cpu_thread.nid))
+            }
+            None => None,
+        };
+        if dump_file_id.is_some() && thread.is_none() {
+            continue;
+        }
+        if !seen.insert((cpu_thread.pid, cpu_thread.nid.clone(), dump_file_id.clone())) {
+            continue;
+        }
+        hot_threads.push(HotThread {
+            file_id: cpu_thread.file_id,
+            time,
+            pid: cpu_thread.pid,
+            nid: cpu_thread.nid,
+            cpu: cpu_thread.cpu,
+            mem: cpu_thread.mem,
+            cpu_time: cpu_thread.cpu_time,
+            command: cpu_thread.command,
+            dump_file_id,
+            thread_id: thread.map(|t| t.id.clone()),
+            thread_name: thread.map(|t| t.thread_name.clone()),
+            status: thread.map(|t| ThreadStatus::try_from(t.thread_status).unwrap_or(ThreadStatus::Unknown)),
+            stack: thread
+                .map(|t| {
+                    t.to_thread()
+                        .frames
+                        .into_iter()
+                        .filter(|frame| matches!(frame.frame, Frame::MethodCall | Frame::NativeMethod))
+                        .filter_map(|frame| frame.signature)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        });
+    }
+    Ok(hot_threads)
+}
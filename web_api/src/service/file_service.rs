@@ -1,9 +1,9 @@
 use std::{collections::HashMap};
 
 use common::{error::AnalysisError};
-use domain::{db::{db_cpu, db_file::{self, DBSourceFile}, db_memory, db_thread::{self, DBThreadInfo}, db_workspace::{self, DBFileWorkSpace}}, model::thread::{StackDumpInfo, ThreadStatus}};
+use domain::{db::{db_cpu, db_cpu_thread, db_file::{self, DBSourceFile}, db_lock, db_memory, db_memory_percent, db_process, db_thread::{self, DBThreadInfo}, db_workspace::{self, DBFileWorkSpace}}, model::thread::{StackDumpInfo, ThreadStatus}};
 use itertools::Itertools;
-use sqlx::{SqlitePool};
+use sqlx::{SqliteConnection, SqlitePool};
 
 /// 获取所有线程文件信息
 /// # Arguments
@@ -49,9 +49,9 @@ pub async fn list_dump_file(pool: &SqlitePool, work_space_id: &str) -> Result<Ve
     }
 }
 
-/// 检查工作空间是否已经创建
-pub async fn exist_work_space(pool: &SqlitePool, path: &str) -> Result<bool, AnalysisError> {
-    Ok(db_workspace::get_by_path(pool, path).await?.is_some())
+/// 查询路径对应的工作空间，未创建时返回None
+pub async fn find_work_space(pool: &SqlitePool, path: &str) -> Result<Option<DBFileWorkSpace>, AnalysisError> {
+    Ok(db_workspace::get_by_path(pool, path).await?)
 }
 
 /// 列出所有工作空间
@@ -63,14 +63,73 @@ pub async fn list_work_space(pool: &SqlitePool) -> Result<Vec<DBFileWorkSpace>,
 
 /// 清理工作空间
 pub async fn clean_work_space(pool: &SqlitePool) -> Result<bool, AnalysisError>{
+    // 删除压缩包解压出的目录
+    for work_space in db_workspace::list(pool).await? {
+        if let Some(root) = work_space.extract_root.filter(|root| std::path::Path::new(root).exists()) {
+            std::fs::remove_dir_all(&root).unwrap_or_else(|err| log::error!("删除解压目录{}出错：{:?}", root, err));
+        }
+    }
     db_workspace::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除工作空间出错：{:?}", err));
     db_file::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除文件信息出错：{:?}", err));
     db_memory::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除内存信息出错：{:?}", err));
+    db_memory_percent::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除内存使用率信息出错：{:?}", err));
     db_cpu::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除CPU信息出错：{:?}", err));
+    db_process::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除进程信息出错：{:?}", err));
+    db_cpu_thread::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除线程CPU信息出错：{:?}", err));
     db_thread::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除线程信息出错：{:?}", err));
+    db_lock::delete_all(pool).await.unwrap_or_else(|err| log::error!("删除锁信息出错：{:?}", err));
     Ok(true)
 }
 
+/// 删除文件及其解析出的数据，文件被删除或修改后重新扫描时使用
+/// # Arguments
+/// * `conn` - 数据库连接，由调用方开启事务
+/// * `file_id` - 文件ID
+pub async fn remove_file(conn: &mut SqliteConnection, file_id: &str) -> Result<(), AnalysisError> {
+    db_memory::delete_by_file(conn, file_id).await?;
+    db_memory_percent::delete_by_file(conn, file_id).await?;
+    db_cpu::delete_by_file(conn, file_id).await?;
+    db_process::delete_by_file(conn, file_id).await?;
+    db_cpu_thread::delete_by_file(conn, file_id).await?;
+    db_thread::delete_by_file(conn, file_id).await?;
+    db_lock::delete_by_file(conn, file_id).await?;
+    db_file::delete(conn, file_id).await?;
+    Ok(())
+}
+
+/// 删除工作空间中的全部文件及其解析出的数据，保留工作空间本身
+/// # Arguments
+/// * `conn` - 数据库连接，由调用方开启事务
+/// * `work_space_id` - 工作空间ID
+/// # Note
+/// 旧版本导入的CPU和内存数据的FILE_ID为空或为文件路径，无法按文件删除，只能按工作空间删除。
+/// 线程和锁信息通过FILE_INFO关联工作空间，需要在删除文件之前删除。
+pub async fn clear_work_space(conn: &mut SqliteConnection, work_space_id: &str) -> Result<(), AnalysisError> {
+    db_memory::delete_by_workspace(conn, work_space_id).await?;
+    db_memory_percent::delete_by_workspace(conn, work_space_id).await?;
+    db_cpu::delete_by_workspace(conn, work_space_id).await?;
+    db_process::delete_by_workspace(conn, work_space_id).await?;
+    db_cpu_thread::delete_by_workspace(conn, work_space_id).await?;
+    db_lock::delete_by_workspace(conn, work_space_id).await?;
+    db_thread::delete_by_workspace(conn, work_space_id).await?;
+    db_file::delete_by_workspace(conn, work_space_id).await?;
+    Ok(())
+}
+
+/// 升级旧版本导入的工作空间，服务启动后处理请求之前执行一次
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// # Note
+/// 旧格式的STACK_INFO缺少方法签名，读取线程时无法还原调用栈，需要从源文件重新解析。
+/// 源文件已不存在的线程无法升级，下次启动时会再次尝试。
+pub async fn upgrade_legacy_work_spaces(pool: &SqlitePool) -> Result<(), AnalysisError> {
+    for work_space_id in db_thread::list_legacy_work_spaces(pool).await? {
+        let count = db_thread::upgrade_stack_info(pool, &work_space_id).await?;
+        log::info!("已升级工作空间{}中{}个线程的堆栈信息", work_space_id, count);
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
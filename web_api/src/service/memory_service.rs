@@ -0,0 +1,27 @@
+use common::error::AnalysisError;
+use domain::{db::{db_memory, db_memory_percent}, model::memory::{GcSeries, MemoryPercentCount}};
+use sqlx::SqlitePool;
+
+/// 统计各个采集时间点的堆内存各区域使用率和GC次数，用于与CPU曲线对照观察内存压力。
+/// 同一工作空间中可能有多个JVM的采样，按JVM和采集方式分别返回序列
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space` - 工作空间ID
+/// # Returns
+/// * `Result<Vec<MemoryPercentCount>, AnalysisError>` - 每个序列以采集时间为横轴的使用率
+pub async fn count_memory_percent(pool: &SqlitePool, work_space: &str) -> Result<Vec<MemoryPercentCount>, AnalysisError> {
+    let percents = db_memory_percent::list_with_source(pool, work_space).await?;
+    Ok(MemoryPercentCount::group(percents))
+}
+
+/// 计算相邻jstat采样之间的GC频率、平均暂停时间，以及老年代增长速率和预计耗尽时间。
+/// 同一工作空间中可能有多个JVM的采样，按JVM和采集方式分别统计
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space` - 工作空间ID
+/// # Returns
+/// * `Result<Vec<GcSeries>, AnalysisError>` - 每个序列分区间和整体的GC活动统计
+pub async fn count_gc_activity(pool: &SqlitePool, work_space: &str) -> Result<Vec<GcSeries>, AnalysisError> {
+    let samples = db_memory::list_with_source(pool, work_space).await?;
+    Ok(GcSeries::group(samples))
+}
@@ -1,3 +1,6 @@
 pub mod thread_dump;
 pub mod file_service;
-pub mod cpu_service;
\ No newline at end of file
+pub mod cpu_service;
+pub mod call_tree;
+pub mod memory_service;
+pub mod timeline_service;
\ No newline at end of file
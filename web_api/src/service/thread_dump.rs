@@ -1,7 +1,6 @@
-use std::collections::HashMap;
-use common::error::AnalysisError;
-use domain::{db::{db_file, db_thread}, model::thread::{PoolThreads, StatusCount, StatusQuery, ThreadContent, ThreadDetail, ThreadStatus, ThreadsQuery}};
-use indexer::idx::index;
+use std::collections::{HashMap, HashSet};
+use common::{error::AnalysisError, file_utils};
+use domain::{db::{db_file::{self, DBSourceFile}, db_lock, db_thread::{self, DBThreadInfo}}, model::{diff::{DumpDiff, MethodDelta, PoolDelta, ThreadPresence, ThreadStateChange}, lock::{self, DeadlockCycle, LockContention}, timeline::{DumpThreads, StuckThread, MIN_STUCK_THRESHOLD, ThreadTimeline, TimelineQuery}, thread::{Frame, PoolThreads, StatusCount, StatusQuery, Thread, ThreadContent, ThreadDetail, ThreadStatus, ThreadsQuery}}};
 use sqlx::SqlitePool;
 
 
@@ -166,7 +165,13 @@ pub async fn get_thread_content(pool: &SqlitePool, thread_id: &str) -> Result<Th
     match db_file::get_file_by_thread(pool, &thread_id).await{
         Ok(file_info) => {
             let content = if file_info.end_line - file_info.start_line > 2  {
-                index::read_lines_from_file(&file_info.file_path, (file_info.start_line  + 2) as usize, file_info.end_line as usize)?
+                file_utils::read_line_range(
+                    &file_info.file_path,
+                    file_info.archive.as_deref(),
+                    file_info.line_index().as_ref(),
+                    (file_info.start_line  + 2) as usize,
+                    file_info.end_line as usize,
+                )?
             }else{
                 vec![]
             };
@@ -183,4 +188,257 @@ pub async fn get_thread_content(pool: &SqlitePool, thread_id: &str) -> Result<Th
             Err(AnalysisError::DBError(format!("没有获取到数据:{}", err)))
         } ,
     }
-}
\ No newline at end of file
+}
+
+/// 跟踪线程在工作空间多个dump文件中的状态变化
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `query` - 工作空间及线程名、nid、tid过滤条件，未指定过滤条件时返回全部线程
+/// # Returns
+/// * `Result<Vec<ThreadTimeline>, AnalysisError>` - 按采集时间排列的线程时间线
+pub async fn list_thread_timeline(pool: &SqlitePool, query: &TimelineQuery) -> Result<Vec<ThreadTimeline>, AnalysisError> {
+    match db_thread::list_samples(pool, &query.work_space_id, &query.name, &query.nid, &query.tid).await{
+        Ok(samples) => Ok(ThreadTimeline::build(samples)),
+        Err(err) => Err(AnalysisError::DBError(format!("查询线程时间线错误:{}", err))),
+    }
+}
+
+/// 找出工作空间中堆栈在连续多个dump中保持不变的RUNNABLE或BLOCKED线程
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space_id` - 工作空间的唯一标识符
+/// * `threshold` - 判定为卡住所需的最少连续dump数
+/// # Returns
+/// * `Result<Vec<StuckThread>, AnalysisError>` - 按连续次数倒序排列的卡住线程，`threshold`小于2时返回`InvalidParam`
+pub async fn find_stuck_threads(pool: &SqlitePool, work_space_id: &str, threshold: usize) -> Result<Vec<StuckThread>, AnalysisError> {
+    if threshold < MIN_STUCK_THRESHOLD {
+        return Err(AnalysisError::InvalidParam(format!("连续dump数{}不能小于{}", threshold, MIN_STUCK_THRESHOLD)));
+    }
+    let files = db_file::list(pool, work_space_id).await?;
+    let mut file_threads: HashMap<String, Vec<(String, Thread)>> = HashMap::new();
+    for info in db_thread::list_by_work_space(pool, work_space_id).await? {
+        file_threads
+            .entry(info.file_id.clone())
+            .or_default()
+            .push((info.id.clone(), info.to_thread()));
+    }
+    let mut dumps: Vec<DumpThreads> = files
+        .into_iter()
+        .filter_map(|file| {
+            file_threads.remove(&file.id).map(|threads| DumpThreads {
+                file_id: file.id,
+                time: file.exe_time,
+                threads,
+            })
+        })
+        .collect();
+    dumps.sort_by_key(|dump| dump.time);
+    Ok(StuckThread::find(&dumps, threshold))
+}
+
+/// 对比同一工作空间中的两个dump文件
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `base_file_id` - 作为基准的dump文件
+/// * `target_file_id` - 与基准对比的dump文件
+/// # Returns
+/// * `Result<DumpDiff, AnalysisError>` - 新增、消失、状态变化的线程，以及线程池和方法的数量变化，
+///   文件不存在或不在同一个工作空间时返回`InvalidParam`
+pub async fn diff_dumps(pool: &SqlitePool, base_file_id: &str, target_file_id: &str) -> Result<DumpDiff, AnalysisError> {
+    let base_file = find_dump_file(pool, base_file_id).await?;
+    let target_file = find_dump_file(pool, target_file_id).await?;
+    if base_file.workspace != target_file.workspace {
+        return Err(AnalysisError::InvalidParam("只能对比同一个工作空间中的dump文件".to_string()));
+    }
+    let base = db_thread::list_threads(pool, base_file_id, &None, &None).await?;
+    let target = db_thread::list_threads(pool, target_file_id, &None, &None).await?;
+    Ok(compare_threads(base_file_id, target_file_id, &base, &target))
+}
+
+async fn find_dump_file(pool: &SqlitePool, file_id: &str) -> Result<DBSourceFile, AnalysisError> {
+    db_file::find(pool, file_id)
+        .await?
+        .ok_or_else(|| AnalysisError::InvalidParam(format!("dump文件{}不存在", file_id)))
+}
+
+/// 增长最多的方法的返回数量
+const TOP_METHOD_LIMIT: usize = 20;
+
+fn compare_threads(base_file_id: &str, target_file_id: &str, base: &[DBThreadInfo], target: &[DBThreadInfo]) -> DumpDiff {
+    let key = |info: &DBThreadInfo| (info.thread_name.clone(), info.tid.clone(), info.nid.clone());
+    let presence = |info: &DBThreadInfo| ThreadPresence {
+        thread_id: info.id.clone(),
+        name: info.thread_name.clone(),
+        nid: info.nid.clone(),
+        status: ThreadStatus::try_from(info.thread_status).unwrap_or(ThreadStatus::Unknown),
+        top_method: info.top_method.clone(),
+    };
+    let base_map: HashMap<_, &DBThreadInfo> = base.iter().map(|info| (key(info), info)).collect();
+    let target_map: HashMap<_, &DBThreadInfo> = target.iter().map(|info| (key(info), info)).collect();
+
+    let appeared = target.iter().filter(|info| !base_map.contains_key(&key(info))).map(presence).collect();
+    let disappeared = base.iter().filter(|info| !target_map.contains_key(&key(info))).map(presence).collect();
+    let changed = target
+        .iter()
+        .filter_map(|after| {
+            let before = base_map.get(&key(after))?;
+            if before.thread_status == after.thread_status {
+                return None;
+            }
+            Some(ThreadStateChange {
+                name: after.thread_name.clone(),
+                nid: after.nid.clone(),
+                base_thread_id: before.id.clone(),
+                target_thread_id: after.id.clone(),
+                base_status: ThreadStatus::try_from(before.thread_status).unwrap_or(ThreadStatus::Unknown),
+                target_status: ThreadStatus::try_from(after.thread_status).unwrap_or(ThreadStatus::Unknown),
+                base_top_method: before.top_method.clone(),
+                target_top_method: after.top_method.clone(),
+            })
+        })
+        .collect();
+
+    let mut pools: HashMap<String, (usize, usize)> = HashMap::new();
+    for info in base {
+        pools.entry(extract_prefix(&info.thread_name)).or_default().0 += 1;
+    }
+    for info in target {
+        pools.entry(extract_prefix(&info.thread_name)).or_default().1 += 1;
+    }
+    let mut pools: Vec<PoolDelta> = pools
+        .into_iter()
+        .map(|(name, (base_count, target_count))| PoolDelta {
+            name,
+            base_count,
+            target_count,
+            delta: target_count as i64 - base_count as i64,
+        })
+        .collect();
+    pools.sort_by(|a, b| b.delta.abs().cmp(&a.delta.abs()).then_with(|| a.name.cmp(&b.name)));
+
+    let base_methods = count_methods(base);
+    let mut target_methods = count_methods(target);
+    let mut samples: Vec<(String, usize, usize)> = base_methods
+        .into_iter()
+        .map(|(method, base_samples)| {
+            let target_samples = target_methods.remove(&method).unwrap_or(0);
+            (method, base_samples, target_samples)
+        })
+        .collect();
+    samples.extend(target_methods.into_iter().map(|(method, target_samples)| (method, 0, target_samples)));
+    let mut methods: Vec<MethodDelta> = samples
+        .into_iter()
+        .filter(|(_, base_samples, target_samples)| target_samples > base_samples)
+        .map(|(method, base_samples, target_samples)| MethodDelta {
+            method,
+            base_samples,
+            target_samples,
+            delta: target_samples as i64 - base_samples as i64,
+        })
+        .collect();
+    methods.sort_by(|a, b| b.delta.cmp(&a.delta).then_with(|| a.method.cmp(&b.method)));
+    methods.truncate(TOP_METHOD_LIMIT);
+
+    DumpDiff {
+        base_file_id: base_file_id.to_string(),
+        target_file_id: target_file_id.to_string(),
+        appeared,
+        disappeared,
+        changed,
+        pools,
+        methods,
+    }
+}
+
+// 统计每个方法出现在多少个线程的堆栈中
+fn count_methods(threads: &[DBThreadInfo]) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for info in threads {
+        let methods: HashSet<String> = info
+            .to_thread()
+            .frames
+            .into_iter()
+            .filter(|frame| matches!(frame.frame, Frame::MethodCall | Frame::NativeMethod))
+            .filter_map(|frame| frame.signature)
+            .collect();
+        for method in methods {
+            *counts.entry(method).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// 检测指定dump文件中的监视器死锁
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `file_id` - 线程dump文件的唯一标识符
+/// # Returns
+/// * `Result<Vec<DeadlockCycle>, AnalysisError>` - 返回文件中的全部死锁环，没有死锁时为空
+pub async fn detect_deadlocks(pool: &SqlitePool, file_id: &str) -> Result<Vec<DeadlockCycle>, AnalysisError> {
+    let threads = read_lock_threads(pool, file_id).await?;
+    Ok(lock::find_deadlocks(&threads))
+}
+
+/// 统计指定dump文件中竞争最激烈的锁
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `file_id` - 线程dump文件的唯一标识符
+/// # Returns
+/// * `Result<Vec<LockContention>, AnalysisError>` - 按等待线程数倒序排列的锁竞争信息
+pub async fn count_lock_contention(pool: &SqlitePool, file_id: &str) -> Result<Vec<LockContention>, AnalysisError> {
+    let threads = read_lock_threads(pool, file_id).await?;
+    Ok(lock::count_contention(&threads))
+}
+
+// 通过LOCK_INFO查询与锁相关的线程，线程中的锁栈帧从THREAD_INFO中还原
+async fn read_lock_threads(pool: &SqlitePool, file_id: &str) -> Result<Vec<Thread>, AnalysisError> {
+    if db_file::find(pool, file_id).await?.is_none() {
+        return Err(AnalysisError::NotFound(format!("文件{}不存在", file_id)));
+    }
+    let threads = db_lock::list_lock_threads(pool, file_id).await?;
+    Ok(threads.iter().map(DBThreadInfo::to_thread).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use domain::{db::db_thread::DBThreadInfo, model::thread::{Thread, ThreadStatus}};
+
+    use super::compare_threads;
+
+    fn info(name: &str, nid: &str, state: &str, frames: &[&str]) -> DBThreadInfo {
+        let mut lines = vec![
+            format!("\"{}\" #1 prio=5 os_prio=0 tid=0x0000000000000001 nid={} runnable [0x0000000000000001]", name, nid),
+            format!("java.lang.Thread.State: {}", state),
+        ];
+        lines.extend(frames.iter().map(|f| f.to_string()));
+        DBThreadInfo::new(&Thread::new(&lines, 0, 0).unwrap(), "file")
+    }
+
+    #[test]
+    fn test_compare_threads() {
+        let base = vec![
+            info("pool-1-thread-1", "0x1", "RUNNABLE", &["at com.example.A.run(A.java:1)"]),
+            info("pool-1-thread-2", "0x2", "RUNNABLE", &["at com.example.A.run(A.java:1)"]),
+            info("timer-0", "0x3", "WAITING", &["at com.example.T.run(T.java:1)"]),
+        ];
+        let target = vec![
+            info("pool-1-thread-1", "0x1", "BLOCKED", &["at com.example.B.lock(B.java:2)", "at com.example.A.run(A.java:1)"]),
+            info("pool-1-thread-2", "0x2", "RUNNABLE", &["at com.example.B.lock(B.java:2)", "at com.example.A.run(A.java:1)"]),
+            info("pool-1-thread-3", "0x4", "RUNNABLE", &["at com.example.B.lock(B.java:2)", "at com.example.A.run(A.java:1)"]),
+        ];
+        let diff = compare_threads("base", "target", &base, &target);
+        assert_eq!(diff.appeared.len(), 1);
+        assert_eq!(diff.appeared[0].name, "pool-1-thread-3");
+        assert_eq!(diff.disappeared.len(), 1);
+        assert_eq!(diff.disappeared[0].name, "timer-0");
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].base_status, ThreadStatus::Runnable);
+        assert_eq!(diff.changed[0].target_status, ThreadStatus::Blocked);
+        let pool = diff.pools.iter().find(|p| p.name == "pool-1-thread").unwrap();
+        assert_eq!((pool.base_count, pool.target_count, pool.delta), (2, 3, 1));
+        assert_eq!(diff.methods[0].method, "com.example.B.lock(B.java:2)");
+        assert_eq!(diff.methods[0].delta, 3);
+        assert_eq!(diff.methods[1].method, "com.example.A.run(A.java:1)");
+        assert_eq!(diff.methods[1].delta, 1);
+    }
+}
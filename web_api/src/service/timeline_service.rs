@@ -0,0 +1,17 @@
+use common::error::AnalysisError;
+use domain::{db::{db_cpu, db_memory, db_thread}, model::correlated::CorrelatedTimeline};
+use sqlx::SqlitePool;
+
+/// 将CPU使用率、各JVM的堆内存使用量和各dump的线程状态数对齐到同一时间轴上
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space` - 工作空间ID
+/// * `interval` - 对齐的时间粒度（秒），同一区间内的采样取平均值
+/// # Returns
+/// * `Result<CorrelatedTimeline, AnalysisError>` - 共享时间轴的各项序列
+pub async fn correlate(pool: &SqlitePool, work_space: &str, interval: Option<i64>) -> Result<CorrelatedTimeline, AnalysisError> {
+    let cpu = db_cpu::count_info(pool, work_space).await?;
+    let memory = db_memory::list_with_source(pool, work_space).await?;
+    let threads = db_thread::count_status_by_dump(pool, work_space).await?;
+    Ok(CorrelatedTimeline::new(interval.unwrap_or_default(), &cpu, &memory, &threads))
+}
@@ -1,11 +1,11 @@
-use std::path::Path;
+use std::{collections::HashMap, path::Path};
 use storage::writer::{LocalWriter, Writer};
 use task::async_task::{AsyncTask, ExecuteContext};
 
-use common::{error::AnalysisError, file_utils, model::file_info::FileInfo};
-use domain::db::{db::ModelTransfer, db_file::{self, DBSourceFile}, db_workspace::{self, DBFileWorkSpace}};
-use parser::parse::{CpuParser, MemoryParser, ParseFile, ThreadParser};
-use sqlx::{SqlitePool};
+use common::{error::AnalysisError, file_utils, model::{file_info::FileInfo, file_rule::FileClassifier, line_index::LineIndex}};
+use domain::{db::{db::ModelTransfer, db_file::{self, DBSourceFile}, db_workspace::{self, DBFileWorkSpace}}, model::{cpu::{Cpu, CpuThread}, memory::{MemoryPercent, MemoryValue}, thread::Thread}};
+use parser::parse::{CpuParser, CpuThreadParser, MemoryParser, ParseFile, ThreadParser};
+use sqlx::{SqliteConnection, SqlitePool};
 
 pub struct ParseFileAsyncTask;
 
@@ -47,60 +47,146 @@ impl AsyncTask for ParseFileAsyncTask{
 /// ```
 /// # Note
 /// 此函数会根据文件类型决定是直接读取文件还是解压缩后读取，并且会更新执行上下文中的进度信息。
-/// 如果是压缩文件，将会解压缩到指定的工作空间中，并解析其中的 CPU、线程和内存信息。
-/// 最终将解析结果写入到数据库中。
-/// # Panics
-/// 如果在读取文件或解压缩过程中发生错误，将会触发 panic。
+/// 如果是压缩文件，将会解压缩到`files.data_root`下以工作空间ID命名的目录中，并解析其中的 CPU、线程和内存信息。
+/// 最终将工作空间和解析结果在同一个事务中写入到数据库中，任一步骤失败都不会留下工作空间和解压目录。
 async fn analysis(pool: &SqlitePool, path: &str, context: &ExecuteContext) -> Result<String, AnalysisError> {
     let file_type: u8 =
         file_utils::get_file_type(path).map_err(|e| AnalysisError::ParseError(e.to_string()))?;
     let source_path = Path::new(path);
-    // 读取或生成文件索引
-    let work_space;
+    let (files_config, analysis_config) = context
+        .config
+        .as_ref()
+        .map(|config| (config.files.clone(), config.analysis.clone()))
+        .unwrap_or_default();
+    let classifier = FileClassifier::new(&files_config.rules)?;
     context.update_progress(1.0, Some("读取文件".to_string())).await;
+    let mut work_space = DBFileWorkSpace::new(path);
     let files: Vec<FileInfo> = match file_type {
-        1 => {
-            work_space = DBFileWorkSpace::new(&path);
-            db_workspace::add(pool, &work_space).await?;
-            file_utils::extract_file(source_path, &work_space.id)
-                .unwrap_or_else(|e| panic!("读取文件时发生错误：{}", e))
-        }
+        1 => file_utils::extract_file(source_path, &work_space.id, &classifier)
+            .map_err(|e| AnalysisError::IoError(format!("读取文件时发生错误：{}", e)))?,
         _ => {
-            work_space = DBFileWorkSpace::new(path);
-            db_workspace::add(pool, &work_space).await?;
+            // 每个工作空间解压到独立的目录中，避免同一目录下的压缩包互相覆盖
+            let target = std::path::absolute(Path::new(&files_config.data_root).join(&work_space.id))
+                .map_err(|e| AnalysisError::IoError(e.to_string()))?;
+            work_space.extract_root = Some(target.to_string_lossy().to_string());
             context.update_progress(2.0, Some("解压".to_string())).await;
-            file_utils::unzip_and_extract_file(source_path, &work_space.id)
-                .unwrap_or_else(|e| panic!("读取文件时发生错误：{}", e))
+            let (files, warnings) = file_utils::unzip_and_extract_file(
+                source_path,
+                &target,
+                &work_space.id,
+                &classifier,
+                analysis_config.max_extract_bytes,
+            )
+            .map_err(|e| AnalysisError::IoError(format!("读取文件时发生错误：{}", e)))
+            .inspect_err(|_| remove_extract_root(&work_space))?;
+            // 超过嵌套层数或解压失败的嵌套压缩包作为普通文件跳过，原因随任务结果返回
+            context.warn(warnings).await;
+            files
         }
     };
+    // 解析和写入失败时不保留工作空间，避免之后打开同一路径时直接返回不完整的工作空间
+    let imported = async {
+        let parsed = parse_files(path, files, context).await?;
+        let mut transaction = pool.begin().await?;
+        db_workspace::add(&mut transaction, &work_space).await?;
+        write_files(&mut transaction, &work_space.id, parsed, context).await?;
+        transaction.commit().await?;
+        Ok::<(), AnalysisError>(())
+    }
+    .await;
+    if let Err(err) = imported {
+        remove_extract_root(&work_space);
+        return Err(err);
+    }
+    context.update_progress(100.0, Some("解析完成".to_string())).await;
+    Ok(work_space.id)
+}
+
+/// 删除工作空间解压出的目录
+fn remove_extract_root(work_space: &DBFileWorkSpace) {
+    if let Some(root) = work_space.extract_root.as_ref().filter(|root| Path::new(root).exists()) {
+        std::fs::remove_dir_all(root).unwrap_or_else(|err| log::error!("删除解压目录{}出错：{:?}", root, err));
+    }
+}
+
+/// 文件中解析出的CPU、线程和内存信息，全部解析完成后再写入数据库
+pub struct ParsedFiles {
+    files: Vec<FileInfo>,
+    cpu_info: Vec<Cpu>,
+    cpu_threads: Vec<CpuThread>,
+    threads_map: HashMap<String, Vec<Thread>>,
+    line_indexes: HashMap<String, LineIndex>,
+    memory_info: Vec<MemoryValue>,
+    memory_percent: Vec<MemoryPercent>,
+}
+
+/// 解析文件中的CPU、线程和内存信息
+/// # Arguments
+/// * `path` - 工作空间对应的文件或文件夹路径
+/// * `files` - 需要解析的文件
+/// * `context` - 执行上下文，用于更新进度和返回警告
+/// # Returns
+/// * `Result<ParsedFiles, AnalysisError>` - 解析结果
+pub async fn parse_files(path: &str, files: Vec<FileInfo>, context: &ExecuteContext) -> Result<ParsedFiles, AnalysisError> {
     context.update_progress(10.0, Some("解析CPU文件".to_string())).await;
+    // CPU、线程和内存文件解析失败时只跳过该文件，原因作为警告随任务结果返回
     let cpu_info = CpuParser::parse(path, &files)?;
+    let cpu_threads = CpuThreadParser::parse(path, &files)?;
+    context.warn(cpu_info.warnings).await;
+    context.warn(cpu_threads.warnings).await;
     context.update_progress(15.0, Some("解析线程文件".to_string())).await;
-    let threads_map = ThreadParser::parse(path, &files)?;
+    let threads = ThreadParser::parse(path, &files)?;
+    context.warn(threads.warnings).await;
+    let (threads_map, line_indexes) = threads.values;
     context.update_progress(25.0, Some("解析内存文件".to_string())).await;
-    let memory_info = MemoryParser::parse(path, &files)?;
-    
+    let memory = MemoryParser::parse(path, &files)?;
+    context.warn(memory.warnings).await;
+    let (memory_info, memory_percent) = memory.values;
+    Ok(ParsedFiles {
+        files,
+        cpu_info: cpu_info.values,
+        cpu_threads: cpu_threads.values,
+        threads_map,
+        line_indexes,
+        memory_info,
+        memory_percent,
+    })
+}
+
+/// 将解析结果与文件信息一起写入工作空间
+/// # Arguments
+/// * `conn` - 数据库连接，由调用方开启事务
+/// * `work_space_id` - 工作空间ID
+/// * `parsed` - 解析结果
+/// * `context` - 执行上下文，用于更新进度
+pub async fn write_files(
+    conn: &mut SqliteConnection,
+    work_space_id: &str,
+    parsed: ParsedFiles,
+    context: &ExecuteContext,
+) -> Result<(), AnalysisError> {
     context.update_progress(30.0, Some("写入文件信息".to_string())).await;
+    let line_indexes = &parsed.line_indexes;
     db_file::batch_add(
-        pool,
-        files
-            .into_iter()
-            .map(|f| DBSourceFile::new(&f, "", &work_space.id))
+        conn,
+        parsed
+            .files
+            .iter()
+            .map(|f| DBSourceFile::new(f, "", work_space_id).with_line_index(line_indexes.get(&f.id)))
             .collect(),
     )
     .await?;
     context.update_progress(35.0, Some("写入CPU信息".to_string())).await;
-    LocalWriter::write_cpu(pool, &work_space.id, &cpu_info).await?;
+    LocalWriter::write_cpu(conn, work_space_id, &parsed.cpu_info).await?;
+    LocalWriter::write_cpu_threads(conn, work_space_id, &parsed.cpu_threads).await?;
     context.update_progress(50.0, Some("写入线程信息".to_string())).await;
-    LocalWriter::write_threads(pool, &work_space.id, &threads_map).await?;
+    LocalWriter::write_threads(conn, work_space_id, &parsed.threads_map).await?;
     context.update_progress(95.0, Some("写入内存信息".to_string())).await;
-    LocalWriter::write_memory(pool, &work_space.id, &memory_info).await?;
-    context.update_progress(100.0, Some("解析完成".to_string())).await;
-    Ok(work_space.id)
+    LocalWriter::write_memory(conn, work_space_id, &parsed.memory_info).await?;
+    LocalWriter::write_memory_percent(conn, work_space_id, &parsed.memory_percent).await?;
+    Ok(())
 }
 
-
-
 #[cfg(test)]
 mod tests {
 
@@ -123,7 +209,7 @@ mod tests {
         let path = Path::new("D:\\dump\\20240726XNJK[非涉密].zip");
         let pool: sqlx::Pool<sqlx::Sqlite> = establish_connection().await;
         #[allow(unreachable_code)]
-        analysis(&pool, path.as_str(), &ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!() }).await.unwrap();
+        analysis(&pool, path.as_str(), &ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!(), config: todo!(), cancel: todo!() }).await.unwrap();
     }
 
     #[actix_rt::test]
@@ -132,7 +218,7 @@ mod tests {
         dotenv().ok();
         let pool: sqlx::Pool<sqlx::Sqlite> = establish_connection().await;
         #[allow(unreachable_code)]
-        analysis(&pool, path.as_str(),&ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!() }).await.unwrap();
+        analysis(&pool, path.as_str(),&ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!(), config: todo!(), cancel: todo!() }).await.unwrap();
     }
     #[actix_rt::test]
     async fn test_walk_dir() {
@@ -140,6 +226,6 @@ mod tests {
         dotenv().ok();
         let pool: sqlx::Pool<sqlx::Sqlite> = establish_connection().await;
         #[allow(unreachable_code)]
-        analysis(&pool, path.as_str(),&ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!() }).await.unwrap();
+        analysis(&pool, path.as_str(),&ExecuteContext{ pool: todo!(), channel: todo!(), param: todo!(), config: todo!(), cancel: todo!() }).await.unwrap();
     }
 }
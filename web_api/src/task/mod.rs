@@ -1,2 +1,4 @@
 pub mod file_prase;
-pub mod stack_build;
\ No newline at end of file
+pub mod rescan;
+pub mod stack_build;
+pub mod watch;
\ No newline at end of file
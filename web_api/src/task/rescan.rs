@@ -0,0 +1,168 @@
+use std::{collections::HashMap, path::Path, sync::{Arc, Mutex}};
+
+use chrono::Utc;
+use common::{error::AnalysisError, file_utils, model::{file_info::FileInfo, file_rule::FileClassifier}};
+use domain::db::{db_file, db_workspace};
+use indexer::cache::global::{CacheKey, GlobalCache};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use task::async_task::{AsyncTask, ExecuteContext};
+
+use crate::{executor::file_prase::{parse_files, write_files}, service::file_service};
+
+/// 重新扫描文件夹工作空间，只解析新增或修改过的文件
+pub struct RescanAsyncTask;
+
+// 每个工作空间一把扫描锁，监听、手动重新扫描和重复打开文件夹可能同时扫描同一个工作空间
+static RESCAN_LOCKS: Lazy<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 重新扫描的结果，分别为新增、修改和删除的文件数
+#[derive(Serialize, Debug, Default)]
+pub struct RescanSummary {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+impl RescanSummary {
+    /// 是否有文件发生变化
+    pub fn is_empty(&self) -> bool {
+        self.added + self.changed + self.removed == 0
+    }
+}
+
+#[async_trait::async_trait]
+impl AsyncTask for RescanAsyncTask {
+    async fn execute(&self, context: &ExecuteContext) -> Result<String, String> {
+        let pool = context.pool.as_ref().ok_or("数据库连接池缺失")?;
+        let work_space_id = context.param.as_ref().ok_or("工作空间ID缺失")?;
+        context.update_progress(1.0, Some("扫描文件夹".to_string())).await;
+        match rescan(pool, work_space_id, context).await {
+            Ok(summary) => {
+                context.update_progress(100.0, Some("扫描完成".to_string())).await;
+                serde_json::to_string(&summary).map_err(|e| e.to_string())
+            }
+            Err(err) => {
+                log::error!("重新扫描工作空间失败:{:?}", err);
+                Err(err.to_string())
+            }
+        }
+    }
+}
+
+/// 对比文件夹中的文件与FILE_INFO中的记录，按路径、大小和修改时间判断文件是否变化
+/// # Arguments
+/// * `pool` - 数据库连接池
+/// * `work_space_id` - 工作空间ID
+/// * `context` - 执行上下文
+/// # Returns
+/// * `Result<RescanSummary, AnalysisError>` - 新增、修改和删除的文件数
+/// # Note
+/// 只支持直接打开的文件夹，压缩包解压出的工作空间内容不会变化。
+/// 先解析新增和修改过的文件，再在同一个事务中删除旧的解析数据并写入新数据，最后刷新工作空间的更新时间。
+/// 同一个工作空间的扫描串行执行，后提交的扫描等待前一次完成后再对比，不会重复导入同一批文件。
+/// 旧版本导入的文件没有记录大小和修改时间，部分解析数据也无法对应到文件，这种工作空间会清空后全部重新解析。
+pub async fn rescan(pool: &SqlitePool, work_space_id: &str, context: &ExecuteContext) -> Result<RescanSummary, AnalysisError> {
+    let lock = RESCAN_LOCKS.lock().unwrap().entry(work_space_id.to_string()).or_default().clone();
+    let result = {
+        let _guard = lock.lock().await;
+        rescan_locked(pool, work_space_id, context).await
+    };
+    drop(lock);
+    release_lock(work_space_id);
+    result
+}
+
+/// 没有其他扫描持有或等待时删除工作空间的扫描锁，避免已删除或不存在的工作空间一直占用
+fn release_lock(work_space_id: &str) {
+    let mut locks = RESCAN_LOCKS.lock().unwrap();
+    if locks.get(work_space_id).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+        locks.remove(work_space_id);
+    }
+}
+
+/// 持有扫描锁时执行扫描，见`rescan`
+async fn rescan_locked(pool: &SqlitePool, work_space_id: &str, context: &ExecuteContext) -> Result<RescanSummary, AnalysisError> {
+    let work_space = db_workspace::get(pool, work_space_id)
+        .await?
+        .ok_or_else(|| AnalysisError::InvalidParam(format!("工作空间{}不存在", work_space_id)))?;
+    let source_path = Path::new(&work_space.file_path);
+    if work_space.extract_root.is_some() || !source_path.is_dir() {
+        return Err(AnalysisError::InvalidParam(format!("{}不是文件夹，无法重新扫描", work_space.file_path)));
+    }
+    let rules = context
+        .config
+        .as_ref()
+        .map(|config| config.files.clone())
+        .unwrap_or_default()
+        .rules;
+    let classifier = FileClassifier::new(&rules)?;
+
+    let mut known: HashMap<String, db_file::DBSourceFile> = db_file::list(pool, work_space_id)
+        .await?
+        .into_iter()
+        .map(|file| (file.file_path.clone(), file))
+        .collect();
+    let legacy = known.values().any(|file| file.file_size.is_none() || file.modified.is_none());
+    let scanned = file_utils::extract_file(source_path, work_space_id, &classifier)
+        .map_err(|e| AnalysisError::IoError(format!("读取文件时发生错误：{}", e)))?;
+
+    let mut summary = RescanSummary::default();
+    let mut files: Vec<FileInfo> = vec![];
+    let mut stale: Vec<String> = vec![];
+    for file in scanned {
+        match known.remove(&file.path) {
+            None => {
+                summary.added += 1;
+                files.push(file);
+            }
+            Some(old) if legacy || old.file_size != file.size || old.modified != file.modified => {
+                summary.changed += 1;
+                stale.push(old.id);
+                files.push(file);
+            }
+            Some(_) => {}
+        }
+    }
+    // 剩下的记录对应的文件已被删除
+    for old in known.into_values() {
+        summary.removed += 1;
+        stale.push(old.id);
+    }
+
+    if !summary.is_empty() {
+        let parsed = parse_files(&work_space.file_path, files, context).await?;
+        let mut transaction = pool.begin().await?;
+        if legacy {
+            file_service::clear_work_space(&mut transaction, work_space_id).await?;
+        } else {
+            for file_id in stale {
+                file_service::remove_file(&mut transaction, &file_id).await?;
+            }
+        }
+        write_files(&mut transaction, work_space_id, parsed, context).await?;
+        transaction.commit().await?;
+        GlobalCache::remove_prefix(&CacheKey::call_tree(work_space_id));
+    }
+    db_workspace::update_time(pool, work_space_id, Utc::now().naive_utc()).await?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_lock() {
+        let lock = RESCAN_LOCKS.lock().unwrap().entry("release".to_string()).or_default().clone();
+        let waiting = RESCAN_LOCKS.lock().unwrap().entry("release".to_string()).or_default().clone();
+        drop(lock);
+        // 还有扫描在等待时保留扫描锁
+        release_lock("release");
+        assert!(RESCAN_LOCKS.lock().unwrap().contains_key("release"));
+        drop(waiting);
+        release_lock("release");
+        assert!(!RESCAN_LOCKS.lock().unwrap().contains_key("release"));
+    }
+}
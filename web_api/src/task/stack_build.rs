@@ -1,8 +1,9 @@
-use domain::{db::{self, db_thread::{self, DBThreadInfo}}, model::{stack::CallTree, thread::Thread}};
+use domain::db::db_thread;
 use indexer::cache::global::{CacheKey, GlobalCache};
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use task::async_task::{AsyncTask, ExecuteContext};
 
+use crate::service::call_tree;
+
 
 pub struct BuildCacheAsyncTask;
 
@@ -12,8 +13,17 @@ impl AsyncTask for BuildCacheAsyncTask{
         let pool = context.pool.as_ref().ok_or("数据库连接池缺失")?;
         let workspace = context.param.as_ref().ok_or("err")?;
         context.update_progress(0.1,Some("开始构建缓存".to_string())).await;
-        match db_thread::list_by_work_space(pool, &workspace).await{
-            Ok(threads) => threads_build(workspace, threads),
+        // 旧版本数据库中的堆栈缺少方法签名，需要先从源文件重新解析
+        match db_thread::upgrade_stack_info(pool, workspace).await {
+            Ok(0) => {},
+            Ok(count) => log::info!("已升级{}个线程的堆栈信息", count),
+            Err(err) => log::error!("升级线程堆栈信息时发生错误:{:?}", err),
+        }
+        match db_thread::list_by_work_space(pool, workspace).await{
+            Ok(threads) => match call_tree::build_call_tree(pool, workspace, threads).await {
+                Ok(tree) => GlobalCache::put(CacheKey::call_tree(workspace), tree),
+                Err(err) => log::error!("构建调用树时发生错误:{:?}", err),
+            },
             Err(err) => log::error!("查询线程信息时发生错误:{:?}", err),
         }
         context.update_progress(1.0, Some("缓存构建完成".to_string())).await;
@@ -23,13 +33,3 @@ impl AsyncTask for BuildCacheAsyncTask{
 
     
 }
-
-fn threads_build(workspace: &str, db_threads: Vec<DBThreadInfo>){
-    let mut threads = Vec::new();
-    for db_thread in db_threads {
-        threads.push(db_thread.to_thread());
-    }
-    let call_tree = CallTree::new(threads);
-    GlobalCache::put(CacheKey::call_tree(workspace), call_tree);
-    ()
-}
\ No newline at end of file
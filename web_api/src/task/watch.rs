@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use domain::db::db_workspace;
+use serde::Serialize;
+use task::async_task::{AsyncTask, ExecuteContext};
+
+use crate::executor::rescan::{rescan, RescanSummary};
+
+/// 监听文件夹工作空间，定时重新扫描并导入新写入的文件，直到任务被取消
+pub struct WatchAsyncTask;
+
+/// 监听任务的累计结果，每次扫描后作为任务结果更新
+#[derive(Serialize, Debug, Default)]
+pub struct WatchSummary {
+    pub scans: usize,
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub last_scan: Option<NaiveDateTime>,
+}
+
+impl WatchSummary {
+    fn record(&mut self, summary: &RescanSummary) {
+        self.scans += 1;
+        self.added += summary.added;
+        self.changed += summary.changed;
+        self.removed += summary.removed;
+        self.last_scan = Some(Utc::now().naive_utc());
+    }
+}
+
+/// 工作空间对应的监听任务ID，同一个工作空间只有一个监听任务
+pub fn watch_task_id(work_space_id: &str) -> String {
+    format!("watch-{}", work_space_id)
+}
+
+#[async_trait::async_trait]
+impl AsyncTask for WatchAsyncTask {
+    async fn execute(&self, context: &ExecuteContext) -> Result<String, String> {
+        let pool = context.pool.as_ref().ok_or("数据库连接池缺失")?;
+        let work_space_id = context.param.as_ref().ok_or("工作空间ID缺失")?;
+        let interval = context
+            .config
+            .as_ref()
+            .map(|config| config.files.watch_interval)
+            .unwrap_or(5)
+            .max(1);
+        let mut total = WatchSummary::default();
+        // 第一次扫描失败说明工作空间不可监听，直接结束任务
+        let summary = rescan(pool, work_space_id, context).await.map_err(|err| err.to_string())?;
+        total.record(&summary);
+        loop {
+            context
+                .update_progress(100.0, Some(format!("监听中，已扫描{}次，新增{}个文件", total.scans, total.added)))
+                .await;
+            if let Ok(result) = serde_json::to_string(&total) {
+                context.publish(result).await;
+            }
+            tokio::select! {
+                _ = context.cancelled() => break,
+                _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+            }
+            match rescan(pool, work_space_id, context).await {
+                Ok(summary) => total.record(&summary),
+                Err(err) => {
+                    // 工作空间被清理后停止监听，其他错误等待下次扫描重试
+                    if matches!(db_workspace::get(pool, work_space_id).await, Ok(None)) {
+                        return Err(format!("工作空间{}已删除", work_space_id));
+                    }
+                    log::error!("监听工作空间{}时扫描失败:{:?}", work_space_id, err);
+                    context.update_progress(100.0, Some(format!("扫描失败:{}", err))).await;
+                }
+            }
+        }
+        context.update_progress(100.0, Some("监听已停止".to_string())).await;
+        serde_json::to_string(&total).map_err(|e| e.to_string())
+    }
+}